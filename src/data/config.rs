@@ -1,15 +1,22 @@
 use serde::Deserialize;
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 /// Encapsulates themeing data.
 #[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct Colors {
     pub album: String,
+    pub album_artist: String,
     pub artist: String,
     pub border: String,
+    pub bpm: String,
+    pub composer: String,
+    pub date: String,
+    pub disc_num: String,
+    pub format: String,
     pub fs_directory: String,
     pub fs_file: String,
+    pub genre: String,
     pub highlight_color: String,
     pub options: String,
     pub paused: String,
@@ -29,10 +36,17 @@ impl Default for Colors {
     fn default() -> Self {
         Colors {
             album: "#00FF00".to_string(),
+            album_artist: "#FF0000".to_string(),
             artist: "#FF0000".to_string(),
             border: "#FFFFFF".to_string(),
+            bpm: "#598EFF".to_string(),
+            composer: "#FF00FF".to_string(),
+            date: "#598EFF".to_string(),
+            disc_num: "#FF00FF".to_string(),
+            format: "#598EFF".to_string(),
             fs_directory: "#598EFF".to_string(),
             fs_file: "#FFFFFF".to_string(),
+            genre: "#00FF00".to_string(),
             highlight_color: "#FF0000".to_string(),
             options: "#FF0000".to_string(),
             paused: "#00FF00".to_string(),
@@ -50,22 +64,71 @@ impl Default for Colors {
     }
 }
 
+impl Colors {
+    /// Returns the color of a playlist column, if the field has one.
+    pub fn field_color(&self, field: &str) -> Option<&str> {
+        Some(match field {
+            "album" => &self.album,
+            "album_artist" => &self.album_artist,
+            "artist" => &self.artist,
+            "bpm" => &self.bpm,
+            "composer" => &self.composer,
+            "disc" => &self.disc_num,
+            "duration" => &self.timestamp,
+            "genre" => &self.genre,
+            "original_date" | "release_date" => &self.date,
+            "title" => &self.title,
+            "track" => &self.track_num,
+            "year" => &self.year,
+            _ => return None,
+        })
+    }
+}
+
+/// Encapsulates auto-DJ data.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct AutoDj {
+    pub avoid_recent: usize,
+    pub enabled: bool,
+    pub lookahead: usize,
+    pub same_artist_weight: u32,
+    pub same_genre_weight: u32,
+    pub sample_size: usize,
+}
+
+impl Default for AutoDj {
+    fn default() -> Self {
+        AutoDj {
+            avoid_recent: 50,
+            enabled: false,
+            lookahead: 3,
+            same_artist_weight: 4,
+            same_genre_weight: 2,
+            sample_size: 30,
+        }
+    }
+}
+
 /// Encapsulates directories data.
 #[derive(Deserialize)]
 #[serde(default)]
 pub struct Directories {
     pub music_directory: PathBuf,
+    pub playlist_directory: PathBuf,
 }
 
 impl Default for Directories {
     fn default() -> Self {
+        let music_directory = dirs::home_dir()
+            .map(|mut path| {
+                path.push("Music");
+                path
+            })
+            .expect("If this fails then the user should set the desired music directory");
         Directories {
-            music_directory: dirs::home_dir()
-                .map(|mut path| {
-                    path.push("Music");
-                    path
-                })
-                .expect("If this fails then the user should set the desired music directory"),
+            playlist_directory: music_directory.join("Playlists"),
+            music_directory,
         }
     }
 }
@@ -74,34 +137,141 @@ impl Default for Directories {
 #[derive(Deserialize)]
 #[serde(default)]
 pub struct Controls {
+    pub enqueue_confirm_threshold: usize,
     pub vol_delta: i16,
 }
 
 impl Default for Controls {
     fn default() -> Self {
-        Controls { vol_delta: 2 }
+        Controls {
+            enqueue_confirm_threshold: 200,
+            vol_delta: 2,
+        }
+    }
+}
+
+/// What playing a file from the browser does with the rest of its folder.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextPlay {
+    /// Only the file itself is queued.
+    Off,
+    /// The queue is replaced by the file and the files after it.
+    Replace,
+    /// The file and the files after it are appended, keeping the old queue.
+    Preserve,
+}
+
+/// Encapsulates playlist generator data.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Generator {
+    pub keep_albums: bool,
+    pub tolerance_secs: u64,
+    pub year_from: Option<i32>,
+    pub year_to: Option<i32>,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator {
+            keep_albums: false,
+            tolerance_secs: 120,
+            year_from: None,
+            year_to: None,
+        }
     }
 }
 
-/// Encapsulates all config.toml parameters.
+/// Encapsulates playback data.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Playback {
+    pub autosave_secs: u64,
+    pub context_play: ContextPlay,
+    pub history_size: usize,
+    pub restart_threshold_secs: u64,
+    pub resume_playing: bool,
+    /// How many times faster gaps longer than `silence_min_ms` play when `silence_skip_gaps` is set.
+    pub silence_gap_speed: u32,
+    pub silence_min_ms: u64,
+    pub silence_skip: bool,
+    pub silence_skip_gaps: bool,
+    pub silence_threshold_db: f32,
+    pub undo_depth: usize,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            autosave_secs: 30,
+            context_play: ContextPlay::Replace,
+            history_size: 100,
+            restart_threshold_secs: 3,
+            resume_playing: false,
+            silence_gap_speed: 8,
+            silence_min_ms: 2000,
+            silence_skip: false,
+            silence_skip_gaps: false,
+            silence_threshold_db: -50.0,
+            undo_depth: 50,
+        }
+    }
+}
+
+/// Encapsulates preview data.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Preview {
+    pub duck_volume: i16,
+    pub length_secs: u64,
+    pub offset: f64,
+    pub pause_main: bool,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Preview {
+            duck_volume: 20,
+            length_secs: 10,
+            offset: 0.3,
+            pause_main: true,
+        }
+    }
+}
+
+/// Encapsulates playlist tab data.
 #[derive(Deserialize)]
 #[serde(default)]
-pub struct ConfigData {
-    pub colors: Colors,
-    pub controls: Controls,
-    pub directories: Directories,
+pub struct PlaylistView {
+    /// Fields shown for each queue item, e.g. "track", "artist", "title", "duration".
+    pub columns: Vec<String>,
 }
 
-impl Default for ConfigData {
+impl Default for PlaylistView {
     fn default() -> Self {
-        ConfigData {
-            colors: Colors::default(),
-            directories: Directories::default(),
-            controls: Controls::default(),
+        PlaylistView {
+            columns: vec!["title".to_string()],
         }
     }
 }
 
+/// Encapsulates all config.toml parameters.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ConfigData {
+    pub auto_dj: AutoDj,
+    pub colors: Colors,
+    pub controls: Controls,
+    pub directories: Directories,
+    pub generator: Generator,
+    pub playback: Playback,
+    pub playlist: PlaylistView,
+    pub preview: Preview,
+    /// Smart playlist names and their queries.
+    pub smart_playlists: BTreeMap<String, String>,
+}
+
 /// Loads the ConfigData from config.toml.
 pub fn load_config() -> ConfigData {
     let config_path = dirs::config_dir()
@@ -0,0 +1,147 @@
+use crate::{
+    data::{config::AutoDj, metadata::file_metadata::FileMetadata},
+    tui::app::App,
+};
+use rand::seq::IndexedRandom;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+/// How many of the latest played tracks a pick is compared against.
+const RECENT_TRACKS: usize = 5;
+/// How often the library is walked again to pick up new files.
+const LIBRARY_REFRESH: Duration = Duration::from_secs(600);
+
+/// Draws a random sample of the library to pick from, skipping avoided paths.
+fn sample_library(library: &[PathBuf], avoid: &HashSet<&Path>, rules: &AutoDj) -> Vec<PathBuf> {
+    let mut rng = rand::rng();
+    let mut sample: Vec<&PathBuf> = library
+        .choose_multiple(&mut rng, rules.sample_size.max(1))
+        .filter(|path| !avoid.contains(path.as_path()))
+        .collect();
+    // A small library may have nothing left to avoid repeats with.
+    if sample.is_empty() {
+        sample = library.choose_multiple(&mut rng, 1).collect();
+    }
+    sample.into_iter().cloned().collect()
+}
+
+/// Picks a track from the sample; this reads the sample's tags, so it runs on a background thread.
+/// Tracks sharing an artist or genre with the recent tracks are more likely to be picked.
+/// Play counts and ratings aren't tracked yet; once they are, they weigh in here the same way.
+fn pick_track(sample: &[PathBuf], recent: &[FileMetadata], rules: &AutoDj) -> Option<FileMetadata> {
+    let candidates: Vec<FileMetadata> = sample
+        .iter()
+        .map(|path| FileMetadata::get_file_data(path))
+        .collect();
+    let shares = |candidate: &Option<String>, field: fn(&FileMetadata) -> &Option<String>| {
+        candidate.is_some() && recent.iter().any(|track| field(track) == candidate)
+    };
+
+    candidates
+        .choose_weighted(&mut rand::rng(), |candidate| {
+            let mut weight = 1;
+            if shares(&candidate.artist, |track| &track.artist) {
+                weight += rules.same_artist_weight;
+            }
+            if shares(&candidate.genre, |track| &track.genre) {
+                weight += rules.same_genre_weight;
+            }
+            weight
+        })
+        .ok()
+        .cloned()
+}
+
+impl App {
+    /// Takes over the library once a background walk of the music directory is done.
+    pub fn poll_library(&mut self) {
+        if let Some(files) = self.library_scanner.finished() {
+            self.library = Some(files);
+        }
+    }
+
+    /// Turns auto-DJ on or off.
+    pub fn handle_auto_dj_toggle(&mut self) {
+        self.auto_dj = !self.auto_dj;
+    }
+
+    /// Keeps a lookahead of tracks from the music directory queued after the current one while auto-DJ is on.
+    /// An empty or ended queue gets a track that starts playing. One track is picked at a time,
+    /// in the background and none until the library walk is done.
+    pub fn top_up_auto_dj(&mut self) {
+        if !self.auto_dj || self.auto_dj_pick.is_some() {
+            return;
+        }
+        if let Some(current) = self.queue.current
+            && self.config.auto_dj.lookahead < self.queue.len() - current
+        {
+            return;
+        }
+
+        // The library is walked in the background and picked up by `poll_library`.
+        if self.library_scanner.is_stale(LIBRARY_REFRESH) {
+            self.library_scanner
+                .request(&self.config.directories.music_directory);
+        }
+        let Some(library) = self.library.as_ref().filter(|library| !library.is_empty()) else {
+            return;
+        };
+
+        let recent: Vec<FileMetadata> = self
+            .history
+            .items
+            .iter()
+            .take(RECENT_TRACKS)
+            .cloned()
+            .collect();
+        let avoid: HashSet<&Path> = self
+            .history
+            .items
+            .iter()
+            .take(self.config.auto_dj.avoid_recent)
+            .chain(self.queue.items.iter())
+            .map(|item| item.file_path.as_path())
+            .collect();
+        let sample = sample_library(library, &avoid, &self.config.auto_dj);
+
+        let (sender, receiver) = mpsc::channel();
+        let rules = self.config.auto_dj.clone();
+        thread::spawn(move || {
+            let _ = sender.send(pick_track(&sample, &recent, &rules));
+        });
+        self.auto_dj_pick = Some(receiver);
+    }
+
+    /// Queues the track auto-DJ picked, starting it if the queue had ended; it is dropped if auto-DJ was turned off.
+    pub fn poll_auto_dj(&mut self) {
+        let Some(receiver) = &self.auto_dj_pick else {
+            return;
+        };
+        let picked = match receiver.try_recv() {
+            Ok(picked) => picked,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.auto_dj_pick = None;
+                return;
+            }
+        };
+        self.auto_dj_pick = None;
+        let Some(item) = picked.filter(|_| self.auto_dj) else {
+            return;
+        };
+
+        let index = self.queue.push(item);
+        match self.queue.current {
+            Some(_) => self.refresh_preload(),
+            None => {
+                self.queue.set_current(index);
+                self.start_current();
+            }
+        }
+    }
+}
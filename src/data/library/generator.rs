@@ -0,0 +1,173 @@
+use crate::{
+    data::{
+        config::Generator,
+        library::scan::{playable_files, sort_album_order},
+        metadata::file_metadata::FileMetadata,
+    },
+    tui::{
+        app::App,
+        popup::{InputAction, Popup},
+    },
+};
+use rand::seq::SliceRandom;
+use std::{
+    path::Path,
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+/// How many random fills are tried; the one closest to the target wins.
+const ATTEMPTS: usize = 20;
+
+/// Parses generator input such as "45" or "45 jazz" into a target in seconds and an optional genre.
+pub fn parse_generator_input(input: &str) -> Option<(f64, Option<String>)> {
+    let input = input.trim();
+    let (minutes, genre) = input.split_once(' ').unwrap_or((input, ""));
+    let minutes: f64 = minutes
+        .parse()
+        .ok()
+        .filter(|&minutes: &f64| 0.0 < minutes)?;
+    let genre = Some(genre.trim().to_string()).filter(|genre| !genre.is_empty());
+    Some((minutes * 60.0, genre))
+}
+
+/// Returns the summed duration of the tracks.
+pub fn duration_of(tracks: &[FileMetadata]) -> f64 {
+    tracks
+        .iter()
+        .filter_map(|track| track.duration_as_secs)
+        .sum()
+}
+
+/// Picks tracks from `pool` adding up to `target_secs` within the configured tolerance.
+/// Albums can be kept whole and in order, and tracks from the preferred years are tried first.
+pub fn generate(pool: &[FileMetadata], target_secs: f64, rules: &Generator) -> Vec<FileMetadata> {
+    let mut chunks: Vec<Vec<FileMetadata>> = match rules.keep_albums {
+        true => {
+            let mut sorted = pool.to_vec();
+            sort_album_order(&mut sorted);
+            sorted
+                .chunk_by(|a, b| a.file_path.parent() == b.file_path.parent() && a.album == b.album)
+                .map(|album| album.to_vec())
+                .collect()
+        }
+        false => pool.iter().map(|track| vec![track.clone()]).collect(),
+    };
+    let preferred = |chunk: &Vec<FileMetadata>| {
+        chunk.iter().any(|track| {
+            track.year.is_some_and(|year| {
+                rules.year_from.is_none_or(|from| from <= year)
+                    && rules.year_to.is_none_or(|to| year <= to)
+            })
+        })
+    };
+    let prefer_years = rules.year_from.is_some() || rules.year_to.is_some();
+
+    let max = target_secs + rules.tolerance_secs as f64;
+    let min = target_secs - rules.tolerance_secs as f64;
+    let mut best: Vec<FileMetadata> = Vec::new();
+    for _ in 0..ATTEMPTS {
+        chunks.shuffle(&mut rand::rng());
+        if prefer_years {
+            chunks.sort_by_key(|chunk| !preferred(chunk));
+        }
+
+        let mut picked: Vec<FileMetadata> = Vec::new();
+        let mut total = 0.0;
+        for chunk in &chunks {
+            let length = duration_of(chunk);
+            if total + length <= max {
+                picked.extend(chunk.iter().cloned());
+                total += length;
+            }
+            if min <= total {
+                break;
+            }
+        }
+
+        if (total - target_secs).abs() < (duration_of(&best) - target_secs).abs() {
+            best = picked;
+        }
+        if min <= total {
+            break;
+        }
+    }
+    best
+}
+
+impl App {
+    /// Asks for a target length (and optionally a genre) to fill from the selected directory,
+    /// or from the current one if a file is selected.
+    pub fn handle_generate(&mut self) {
+        let dir = match self.file_browser.entries.get(self.file_browser.selected) {
+            Some(path) if path.is_dir() => path.clone(),
+            _ => self.file_browser.current_dir.clone(),
+        };
+        self.popup = Some(Popup::Input {
+            prompt: format!(
+                "Minutes of music from {} (optionally followed by a genre):",
+                self.format_display_path(&dir)
+            ),
+            input: String::new(),
+            action: InputAction::Generate(dir),
+        });
+    }
+
+    /// Collects the tracks of known length under `dir`, optionally limited to a genre, on a background thread.
+    /// The popup shows that tags are being read until `poll_generator_pool` makes a first fill.
+    pub fn start_generator(&mut self, dir: &Path, target_secs: f64, genre: Option<String>) {
+        let (sender, receiver) = mpsc::channel();
+        let dir = dir.to_path_buf();
+        thread::spawn(move || {
+            let pool: Vec<FileMetadata> = playable_files(&dir)
+                .iter()
+                .map(|path| FileMetadata::load_file_data(path))
+                .filter(|track| track.duration_as_secs.is_some())
+                .filter(|track| {
+                    genre.as_ref().is_none_or(|genre| {
+                        track
+                            .genre
+                            .as_ref()
+                            .is_some_and(|track_genre| track_genre.eq_ignore_ascii_case(genre))
+                    })
+                })
+                .collect();
+            let _ = sender.send(pool);
+        });
+        self.generator_pool = Some(receiver);
+        self.popup = Some(Popup::Generated {
+            pool: Vec::new(),
+            reading: true,
+            target_secs,
+            tracks: Vec::new(),
+        });
+    }
+
+    /// Makes a first fill once the pool is read; the pool is dropped if the popup was closed.
+    pub fn poll_generator_pool(&mut self) {
+        let Some(receiver) = &self.generator_pool else {
+            return;
+        };
+        let found = match receiver.try_recv() {
+            Ok(pool) => pool,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.generator_pool = None;
+                return;
+            }
+        };
+        self.generator_pool = None;
+        if let Some(Popup::Generated {
+            pool,
+            reading,
+            target_secs,
+            tracks,
+        }) = &mut self.popup
+            && *reading
+        {
+            *tracks = generate(&found, *target_secs, &self.config.generator);
+            *pool = found;
+            *reading = false;
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod auto_dj;
+pub mod generator;
+pub mod query;
+pub mod scan;
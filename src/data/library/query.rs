@@ -0,0 +1,644 @@
+use crate::data::{library::scan::natural_cmp, metadata::file_metadata::FileMetadata};
+use rand::seq::SliceRandom;
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, cmp::Ordering};
+
+/// A track field a query can test or sort by.
+#[derive(Clone, Copy)]
+pub enum Field {
+    Album,
+    AlbumArtist,
+    Artist,
+    Bpm,
+    Comment,
+    Composer,
+    Disc,
+    DiscTotal,
+    Duration,
+    Genre,
+    MusicBrainzAlbumId,
+    MusicBrainzArtistId,
+    MusicBrainzTrackId,
+    OriginalDate,
+    Path,
+    ReleaseDate,
+    Title,
+    Track,
+    TrackTotal,
+    Year,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Field, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "album" => Ok(Field::Album),
+            "album_artist" | "albumartist" => Ok(Field::AlbumArtist),
+            "artist" => Ok(Field::Artist),
+            "bpm" => Ok(Field::Bpm),
+            "comment" => Ok(Field::Comment),
+            "composer" => Ok(Field::Composer),
+            "disc" => Ok(Field::Disc),
+            "disc_total" | "discs" => Ok(Field::DiscTotal),
+            "duration" | "length" => Ok(Field::Duration),
+            "genre" => Ok(Field::Genre),
+            "musicbrainz_album_id" => Ok(Field::MusicBrainzAlbumId),
+            "musicbrainz_artist_id" => Ok(Field::MusicBrainzArtistId),
+            "musicbrainz_track_id" => Ok(Field::MusicBrainzTrackId),
+            "original_date" => Ok(Field::OriginalDate),
+            "path" | "file" => Ok(Field::Path),
+            "release_date" | "date" => Ok(Field::ReleaseDate),
+            "title" => Ok(Field::Title),
+            "track" => Ok(Field::Track),
+            "track_total" | "tracks" => Ok(Field::TrackTotal),
+            "year" => Ok(Field::Year),
+            _ => Err(format!("unknown field '{}'", name)),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Field::Bpm
+                | Field::Disc
+                | Field::DiscTotal
+                | Field::Duration
+                | Field::Track
+                | Field::TrackTotal
+                | Field::Year
+        )
+    }
+
+    fn text<'a>(&self, track: &'a FileMetadata) -> Option<Cow<'a, str>> {
+        match self {
+            Field::Album => track.album.as_deref().map(Into::into),
+            Field::AlbumArtist => track.album_artist.as_deref().map(Into::into),
+            Field::Artist => track.artist.as_deref().map(Into::into),
+            Field::Comment => track.comment.as_deref().map(Into::into),
+            Field::Composer => track.composer.as_deref().map(Into::into),
+            Field::Genre => track.genre.as_deref().map(Into::into),
+            Field::MusicBrainzAlbumId => track.musicbrainz_album_id.as_deref().map(Into::into),
+            Field::MusicBrainzArtistId => track.musicbrainz_artist_id.as_deref().map(Into::into),
+            Field::MusicBrainzTrackId => track.musicbrainz_track_id.as_deref().map(Into::into),
+            Field::OriginalDate => track.original_date.as_deref().map(Into::into),
+            Field::Path => Some(track.file_path.to_string_lossy()),
+            Field::ReleaseDate => track.release_date.as_deref().map(Into::into),
+            Field::Title => Some(track.title.as_deref().unwrap_or(&track.raw_file).into()),
+            _ => None,
+        }
+    }
+
+    fn number(&self, track: &FileMetadata) -> Option<f64> {
+        match self {
+            Field::Bpm => track.bpm.map(f64::from),
+            Field::Disc => track.disc_number.map(f64::from),
+            Field::DiscTotal => track.disc_total.map(f64::from),
+            Field::Duration => track.duration_as_secs,
+            Field::Track => track.track_number.map(f64::from),
+            Field::TrackTotal => track.track_total.map(f64::from),
+            Field::Year => track.year.map(f64::from),
+            _ => None,
+        }
+    }
+}
+
+/// What a field is tested against.
+enum Test {
+    /// Text fields contain the value, ignoring case.
+    Contains(String),
+    /// Text fields equal the value, ignoring case.
+    Equals(String),
+    Matches(Regex),
+    /// Numeric fields lie within the bounds, both inclusive.
+    Range(Option<f64>, Option<f64>),
+    Less(f64),
+    Greater(f64),
+}
+
+/// A parsed filter.
+enum Expr {
+    All,
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Test(Field, Test),
+    /// A bare word, looked for in the artist, album, title and path.
+    Word(String),
+}
+
+/// A parsed smart playlist query: a filter, then how to order the tracks and how many to keep.
+pub struct Query {
+    filter: Expr,
+    limit: Option<usize>,
+    shuffle: bool,
+    sort: Vec<(Field, bool)>,
+}
+
+/// A query token. Text from `quoted_from` on was quoted, so it is never a keyword or operator.
+enum Token {
+    Open,
+    Close,
+    Not,
+    Word {
+        text: String,
+        quoted_from: Option<usize>,
+    },
+}
+
+/// Splits a query into tokens. Quotes may start anywhere in a word, as in `artist:"Kenny G"`.
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut text = String::new();
+                let mut quoted_from = None;
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        quoted_from = quoted_from.or(Some(text.len()));
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some(c) => text.push(c),
+                                None => return Err("unclosed quote".to_string()),
+                            }
+                        }
+                    } else {
+                        text.push(c);
+                    }
+                }
+                tokens.push(Token::Word { text, quoted_from });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses a number, or a "m:ss" length into seconds.
+fn parse_number(text: &str) -> Result<f64, String> {
+    let parsed = match text.split_once(':') {
+        Some((minutes, seconds)) => minutes
+            .parse::<f64>()
+            .and_then(|minutes| {
+                seconds
+                    .parse::<f64>()
+                    .map(|seconds| minutes * 60.0 + seconds)
+            })
+            .ok(),
+        None => text.parse().ok(),
+    };
+    parsed.ok_or_else(|| format!("'{}' is not a number", text))
+}
+
+/// Parses the value after `field:`: a regex in slashes, a range `a..b`, a number or text.
+fn parse_value(field: Field, value: &str) -> Result<Test, String> {
+    if 2 <= value.len() && value.starts_with('/') && value.ends_with('/') {
+        let pattern = &value[1..value.len() - 1];
+        return RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Test::Matches)
+            .map_err(|e| format!("bad regex '{}': {}", pattern, e));
+    }
+    if !field.is_numeric() {
+        return Ok(Test::Contains(value.to_lowercase()));
+    }
+    let bound = |text: &str| match text {
+        "" => Ok(None),
+        text => parse_number(text).map(Some),
+    };
+    match value.split_once("..") {
+        Some((from, to)) => Ok(Test::Range(bound(from)?, bound(to)?)),
+        None => {
+            let number = parse_number(value)?;
+            Ok(Test::Range(Some(number), Some(number)))
+        }
+    }
+}
+
+/// Parses a word: `field:value`, `field=value`, `field>=number` and the like, or a bare word.
+fn parse_term(text: &str, quoted_from: Option<usize>) -> Result<Expr, String> {
+    let operator = text[..quoted_from.unwrap_or(text.len())].find([':', '=', '<', '>']);
+    let Some(at) = operator else {
+        return Ok(Expr::Word(text.to_lowercase()));
+    };
+    let field = Field::parse(&text[..at])?;
+    let rest = &text[at..];
+
+    let test = if let Some(value) = rest.strip_prefix(':') {
+        parse_value(field, value)?
+    } else if let Some(value) = rest.strip_prefix(">=") {
+        Test::Range(Some(parse_number(value)?), None)
+    } else if let Some(value) = rest.strip_prefix("<=") {
+        Test::Range(None, Some(parse_number(value)?))
+    } else if let Some(value) = rest.strip_prefix('>') {
+        Test::Greater(parse_number(value)?)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        Test::Less(parse_number(value)?)
+    } else {
+        let value = rest.trim_start_matches('=');
+        match field.is_numeric() {
+            true => parse_value(field, value)?,
+            false => Test::Equals(value.to_lowercase()),
+        }
+    };
+    if field.is_numeric() != matches!(test, Test::Range(..) | Test::Less(_) | Test::Greater(_))
+        && !matches!(test, Test::Matches(_))
+    {
+        return Err(format!("'{}' compares a text field with a number", text));
+    }
+    Ok(Expr::Test(field, test))
+}
+
+/// A recursive descent parser over the tokens; `or` binds loosest, then `and`, then `not`.
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.at), Some(Token::Word { text, quoted_from: None }) if text.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            self.at += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    /// Terms next to each other are joined by `and`, with or without the keyword.
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        loop {
+            if self.keyword("and") {
+                self.at += 1;
+            } else if self.at == self.tokens.len()
+                || self.keyword("or")
+                || matches!(self.tokens[self.at], Token::Close)
+            {
+                return Ok(expr);
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if matches!(self.tokens.get(self.at), Some(Token::Not)) || self.keyword("not") {
+            self.at += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.at);
+        self.at += 1;
+        match token {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.tokens.get(self.at) {
+                    Some(Token::Close) => {
+                        self.at += 1;
+                        Ok(expr)
+                    }
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(Token::Word { text, quoted_from }) => parse_term(text, *quoted_from),
+            Some(_) => Err("unexpected ')' or '!'".to_string()),
+            None => Err("query ends too early".to_string()),
+        }
+    }
+}
+
+/// Parses a query such as `genre:jazz year:1955..1965 !artist:"Kenny G" sort:-year limit:50`.
+/// `sort:field` (`-field` for descending, `random` to shuffle) and `limit:n` may appear anywhere.
+pub fn parse_query(query: &str) -> Result<Query, String> {
+    let mut sort = Vec::new();
+    let mut limit = None;
+    let mut shuffle = false;
+    let mut tokens = Vec::new();
+
+    for token in tokenize(query)? {
+        let Token::Word {
+            text,
+            quoted_from: None,
+        } = &token
+        else {
+            tokens.push(token);
+            continue;
+        };
+        if let Some(key) = text.strip_prefix("sort:") {
+            match key {
+                "random" => shuffle = true,
+                key => match key.strip_prefix('-') {
+                    Some(key) => sort.push((Field::parse(key)?, true)),
+                    None => sort.push((Field::parse(key)?, false)),
+                },
+            }
+        } else if let Some(count) = text.strip_prefix("limit:") {
+            limit = Some(
+                count
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a count", count))?,
+            );
+        } else {
+            tokens.push(token);
+        }
+    }
+
+    let filter = match tokens.is_empty() {
+        true => Expr::All,
+        false => {
+            let mut parser = Parser { tokens, at: 0 };
+            let filter = parser.or()?;
+            if parser.at < parser.tokens.len() {
+                return Err("unexpected ')'".to_string());
+            }
+            filter
+        }
+    };
+    Ok(Query {
+        filter,
+        limit,
+        shuffle,
+        sort,
+    })
+}
+
+impl Test {
+    fn matches(&self, field: Field, track: &FileMetadata) -> bool {
+        match self {
+            Test::Contains(value) => field
+                .text(track)
+                .is_some_and(|text| text.to_lowercase().contains(value)),
+            Test::Equals(value) => field
+                .text(track)
+                .is_some_and(|text| text.to_lowercase() == *value),
+            Test::Matches(regex) => match field.is_numeric() {
+                true => field
+                    .number(track)
+                    .is_some_and(|number| regex.is_match(&number.to_string())),
+                false => field.text(track).is_some_and(|text| regex.is_match(&text)),
+            },
+            Test::Range(from, to) => field.number(track).is_some_and(|number| {
+                from.is_none_or(|from| from <= number) && to.is_none_or(|to| number <= to)
+            }),
+            Test::Less(bound) => field.number(track).is_some_and(|number| number < *bound),
+            Test::Greater(bound) => field.number(track).is_some_and(|number| *bound < number),
+        }
+    }
+}
+
+impl Expr {
+    fn matches(&self, track: &FileMetadata) -> bool {
+        match self {
+            Expr::All => true,
+            Expr::And(a, b) => a.matches(track) && b.matches(track),
+            Expr::Or(a, b) => a.matches(track) || b.matches(track),
+            Expr::Not(expr) => !expr.matches(track),
+            Expr::Test(field, test) => test.matches(*field, track),
+            Expr::Word(word) => [Field::Artist, Field::Album, Field::Title, Field::Path]
+                .iter()
+                .any(|field| Test::Contains(word.clone()).matches(*field, track)),
+        }
+    }
+}
+
+/// Orders two tracks by a field; tracks missing it go last either way.
+fn compare_by(field: Field, descending: bool, a: &FileMetadata, b: &FileMetadata) -> Ordering {
+    let order = match field.is_numeric() {
+        true => match (field.number(a), field.number(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => return b.is_some().cmp(&a.is_some()),
+        },
+        false => match (field.text(a), field.text(b)) {
+            (Some(a), Some(b)) => natural_cmp(&a, &b),
+            (a, b) => return b.is_some().cmp(&a.is_some()),
+        },
+    };
+    match descending {
+        true => order.reverse(),
+        false => order,
+    }
+}
+
+impl Query {
+    /// Picks the matching tracks, sorted and limited as the query asks.
+    pub fn run(&self, tracks: Vec<FileMetadata>) -> Vec<FileMetadata> {
+        let mut matched: Vec<FileMetadata> = tracks
+            .into_iter()
+            .filter(|track| self.filter.matches(track))
+            .collect();
+        if self.shuffle {
+            matched.shuffle(&mut rand::rng());
+        }
+        if !self.sort.is_empty() {
+            matched.sort_by(|a, b| {
+                self.sort
+                    .iter()
+                    .map(|&(field, descending)| compare_by(field, descending, a, b))
+                    .find(|order| order.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        if let Some(limit) = self.limit {
+            matched.truncate(limit);
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn track(
+        title: &str,
+        artist: &str,
+        genre: &str,
+        year: i32,
+        duration: Option<f64>,
+    ) -> FileMetadata {
+        let mut track = FileMetadata::with_file_only(Path::new(&format!("/music/{}.flac", year)));
+        track.title = Some(title.to_string());
+        track.artist = Some(artist.to_string());
+        track.genre = Some(genre.to_string());
+        track.year = Some(year);
+        track.set_duration(duration);
+        if artist == "Miles Davis" {
+            track.album = Some("Kind of Blue".to_string());
+        }
+        track
+    }
+
+    fn library() -> Vec<FileMetadata> {
+        vec![
+            track("Songbird", "Kenny G", "Jazz", 1986, Some(275.0)),
+            track("So What", "Miles Davis", "Jazz", 1959, Some(562.0)),
+            track("Blue in Green", "Miles Davis", "Jazz", 1959, Some(337.0)),
+            track("Paranoid", "Black Sabbath", "Metal", 1970, Some(168.0)),
+            track("Kenny's Song", "Kenny Rogers", "Country", 1978, None),
+        ]
+    }
+
+    fn titles(query: &str) -> Vec<String> {
+        parse_query(query)
+            .unwrap_or_else(|e| panic!("{}: {}", query, e))
+            .run(library())
+            .into_iter()
+            .map(|track| track.display_title())
+            .collect()
+    }
+
+    #[test]
+    fn queries_pick_and_order_tracks() {
+        let cases: &[(&str, &[&str])] = &[
+            (
+                "",
+                &[
+                    "Songbird",
+                    "So What",
+                    "Blue in Green",
+                    "Paranoid",
+                    "Kenny's Song",
+                ],
+            ),
+            // Quoting
+            ("artist:\"Kenny G\"", &["Songbird"]),
+            ("artist:kenny", &["Songbird", "Kenny's Song"]),
+            ("artist=\"miles davis\"", &["So What", "Blue in Green"]),
+            ("\"kenny's song\"", &["Kenny's Song"]),
+            ("blue", &["So What", "Blue in Green"]),
+            // Precedence: `not` binds tightest, then `and`, then `or`.
+            (
+                "genre:jazz !artist:\"Kenny G\"",
+                &["So What", "Blue in Green"],
+            ),
+            ("not genre:jazz year:1970", &["Paranoid"]),
+            (
+                "!genre:jazz or year:1959",
+                &["So What", "Blue in Green", "Paranoid", "Kenny's Song"],
+            ),
+            (
+                "genre:country or genre:metal and year<1975",
+                &["Paranoid", "Kenny's Song"],
+            ),
+            (
+                "(genre:country or genre:metal) and year<1975",
+                &["Paranoid"],
+            ),
+            (
+                "genre:metal or (genre:jazz !year:1959)",
+                &["Songbird", "Paranoid"],
+            ),
+            // Ranges, open at either end
+            ("year:1959", &["So What", "Blue in Green"]),
+            ("year:1970..", &["Songbird", "Paranoid", "Kenny's Song"]),
+            ("year:..1960", &["So What", "Blue in Green"]),
+            ("year:1960..1980", &["Paranoid", "Kenny's Song"]),
+            ("year>=1978", &["Songbird", "Kenny's Song"]),
+            ("year>1978", &["Songbird"]),
+            // m:ss lengths
+            ("length:4:00..5:00", &["Songbird"]),
+            ("length>=9:00", &["So What"]),
+            ("duration<3:00", &["Paranoid"]),
+            ("length:..200", &["Paranoid"]),
+            // Regexes, ignoring case
+            ("artist:/g$/", &["Songbird"]),
+            ("title:\"/^(blue|so )/\"", &["So What", "Blue in Green"]),
+            ("year:/^19[67]/", &["Paranoid", "Kenny's Song"]),
+            // Sorting and limits
+            (
+                "sort:-year",
+                &[
+                    "Songbird",
+                    "Kenny's Song",
+                    "Paranoid",
+                    "So What",
+                    "Blue in Green",
+                ],
+            ),
+            (
+                "genre:jazz sort:-length",
+                &["So What", "Blue in Green", "Songbird"],
+            ),
+            (
+                "sort:length",
+                &[
+                    "Paranoid",
+                    "Songbird",
+                    "Blue in Green",
+                    "So What",
+                    "Kenny's Song",
+                ],
+            ),
+            (
+                "sort:year sort:title",
+                &[
+                    "Blue in Green",
+                    "So What",
+                    "Paranoid",
+                    "Kenny's Song",
+                    "Songbird",
+                ],
+            ),
+            ("sort:year limit:2", &["So What", "Blue in Green"]),
+            ("limit:1 genre:metal", &["Paranoid"]),
+            ("limit:0", &[]),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(titles(query), *expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn bad_queries_say_what_is_wrong() {
+        let cases = [
+            ("artist:\"Kenny G", "unclosed quote"),
+            ("colour:red", "unknown field 'colour'"),
+            ("sort:colour", "unknown field 'colour'"),
+            ("limit:many", "'many' is not a count"),
+            ("year:abc", "'abc' is not a number"),
+            ("year:1990..x", "'x' is not a number"),
+            ("length>4:xx", "'4:xx' is not a number"),
+            ("title>3", "'title>3' compares a text field with a number"),
+            ("title:\"/(/\"", "bad regex '('"),
+            ("(genre:jazz", "missing ')'"),
+            ("genre:jazz)", "unexpected ')'"),
+            ("()", "unexpected ')' or '!'"),
+            ("!", "query ends too early"),
+            ("genre:jazz and", "query ends too early"),
+        ];
+        for (query, expected) in cases {
+            match parse_query(query) {
+                Ok(_) => panic!("{} parsed", query),
+                Err(e) => assert!(e.starts_with(expected), "{}: {}", query, e),
+            }
+        }
+    }
+}
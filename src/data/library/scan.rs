@@ -0,0 +1,184 @@
+use crate::{
+    data::metadata::file_metadata::FileMetadata, tui::app::PLAYABLE,
+    tui::render::fs_browser::FileBrowser,
+};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Recursively collects every playable, non-hidden file under `dir`.
+/// Symlinked directories are followed, but each directory is only walked once so link loops end.
+pub fn playable_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    let mut visited = HashSet::new();
+
+    while let Some(dir) = pending.pop() {
+        let Ok(canonical) = fs::canonicalize(&dir) else {
+            continue;
+        };
+        if !visited.insert(canonical) {
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if FileBrowser::is_hidden(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if FileBrowser::is_playable_file(&path, &PLAYABLE) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// Walks the music directory on a background thread, so a large library never stalls the tui.
+pub struct LibraryScanner {
+    pending: Option<Receiver<Vec<PathBuf>>>,
+    scanned_at: Option<Instant>,
+}
+
+impl LibraryScanner {
+    pub fn new() -> Self {
+        Self {
+            pending: None,
+            scanned_at: None,
+        }
+    }
+
+    /// Starts walking `dir` unless a walk is already running.
+    pub fn request(&mut self, dir: &Path) {
+        if self.pending.is_some() {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let dir = dir.to_path_buf();
+        thread::spawn(move || {
+            let _ = sender.send(playable_files(&dir));
+        });
+        self.pending = Some(receiver);
+    }
+
+    /// Returns true if the last finished walk is older than `max_age`, or there was none.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.scanned_at
+            .is_none_or(|scanned_at| max_age <= scanned_at.elapsed())
+    }
+
+    /// Returns the files found once the running walk is done, without waiting.
+    pub fn finished(&mut self) -> Option<Vec<PathBuf>> {
+        let files = match self.pending.as_ref()?.try_recv() {
+            Ok(files) => files,
+            Err(TryRecvError::Empty) => return None,
+            // The walk died, so the next request starts over.
+            Err(TryRecvError::Disconnected) => {
+                self.pending = None;
+                return None;
+            }
+        };
+        self.pending = None;
+        self.scanned_at = Some(Instant::now());
+        Some(files)
+    }
+}
+
+/// Compares two strings so that embedded numbers sort by value ("track2" < "track10"), ignoring case.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(digit);
+                    }
+                    digits
+                };
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let (x_trim, y_trim) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_trim
+                    .len()
+                    .cmp(&y_trim.len())
+                    .then_with(|| x_trim.cmp(y_trim))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Sorts tracks the way albums are laid out: by folder, then disc number, then track number, then file name.
+/// Folders keep a discography's albums (and "CD1"/"CD2" folders) together.
+pub fn sort_album_order(items: &mut [FileMetadata]) {
+    let folder = |item: &FileMetadata| {
+        item.file_path
+            .parent()
+            .map(|parent| parent.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    items.sort_by(|a, b| {
+        natural_cmp(&folder(a), &folder(b))
+            .then_with(|| a.disc_number.unwrap_or(0).cmp(&b.disc_number.unwrap_or(0)))
+            .then_with(|| {
+                a.track_number
+                    .unwrap_or(0)
+                    .cmp(&b.track_number.unwrap_or(0))
+            })
+            .then_with(|| natural_cmp(&a.raw_file, &b.raw_file))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_are_walked_once() {
+        let root = std::env::temp_dir().join(format!("rmpr-scan-{}", std::process::id()));
+        let album = root.join("album");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(album.join("01.mp3"), b"").unwrap();
+        std::os::unix::fs::symlink("..", album.join("loop")).unwrap();
+
+        let files = playable_files(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(files, [album.join("01.mp3")]);
+    }
+
+    #[test]
+    fn numbers_sort_by_value() {
+        let mut names = ["track10", "Track2", "track02", "track1"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["track1", "Track2", "track02", "track10"]);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::data::{
+    metadata::file_metadata::FileMetadata,
+    session::{data_file, write_data_file},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, MutexGuard},
+    time::UNIX_EPOCH,
+};
+
+const CACHE_FILE: &str = "metadata.toml";
+/// Bumped whenever more is read from files or the file's layout changes, so files cached before are read again.
+const CACHE_VERSION: u32 = 4;
+
+/// The tags read from a file, along with what the file looked like when they were read.
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedFile {
+    mtime_nanos: u64,
+    size: u64,
+    album: Option<String>,
+    album_artist: Option<String>,
+    artist: Option<String>,
+    bpm: Option<u16>,
+    comment: Option<String>,
+    composer: Option<String>,
+    disc_number: Option<u16>,
+    disc_total: Option<u16>,
+    duration_as_secs: Option<f64>,
+    /// True if the length was estimated rather than read or counted.
+    #[serde(default)]
+    duration_estimated: bool,
+    genre: Option<String>,
+    musicbrainz_album_id: Option<String>,
+    musicbrainz_artist_id: Option<String>,
+    musicbrainz_track_id: Option<String>,
+    original_date: Option<String>,
+    release_date: Option<String>,
+    title: Option<String>,
+    track_number: Option<u16>,
+    track_total: Option<u16>,
+    year: Option<i32>,
+}
+
+impl CachedFile {
+    fn of(data: &FileMetadata, duration_estimated: bool, (mtime_nanos, size): (u64, u64)) -> Self {
+        Self {
+            mtime_nanos,
+            size,
+            album: data.album.clone(),
+            album_artist: data.album_artist.clone(),
+            artist: data.artist.clone(),
+            bpm: data.bpm,
+            comment: data.comment.clone(),
+            composer: data.composer.clone(),
+            disc_number: data.disc_number,
+            disc_total: data.disc_total,
+            duration_as_secs: data.duration_as_secs,
+            duration_estimated,
+            genre: data.genre.clone(),
+            musicbrainz_album_id: data.musicbrainz_album_id.clone(),
+            musicbrainz_artist_id: data.musicbrainz_artist_id.clone(),
+            musicbrainz_track_id: data.musicbrainz_track_id.clone(),
+            original_date: data.original_date.clone(),
+            release_date: data.release_date.clone(),
+            title: data.title.clone(),
+            track_number: data.track_number,
+            track_total: data.track_total,
+            year: data.year,
+        }
+    }
+
+    /// Rebuilds the metadata for `path`, which may differ from the cached canonical path.
+    fn to_metadata(&self, path: &Path) -> FileMetadata {
+        let mut data = FileMetadata::with_file_only(path);
+        data.album = self.album.clone();
+        data.album_artist = self.album_artist.clone();
+        data.artist = self.artist.clone();
+        data.bpm = self.bpm;
+        data.comment = self.comment.clone();
+        data.composer = self.composer.clone();
+        data.disc_number = self.disc_number;
+        data.disc_total = self.disc_total;
+        data.genre = self.genre.clone();
+        data.musicbrainz_album_id = self.musicbrainz_album_id.clone();
+        data.musicbrainz_artist_id = self.musicbrainz_artist_id.clone();
+        data.musicbrainz_track_id = self.musicbrainz_track_id.clone();
+        data.original_date = self.original_date.clone();
+        data.release_date = self.release_date.clone();
+        data.title = self.title.clone();
+        data.track_number = self.track_number;
+        data.track_total = self.track_total;
+        data.year = self.year;
+        data.set_duration(self.duration_as_secs);
+        data
+    }
+}
+
+/// One file's entry as written to metadata.toml.
+#[derive(Serialize, Deserialize)]
+struct SavedFile<P, F> {
+    path: P,
+    #[serde(flatten)]
+    file: F,
+}
+
+/// The cache as written to metadata.toml. Entries are appended as they are read, so a path
+/// may appear more than once; the last entry counts.
+#[derive(Serialize, Deserialize)]
+struct SavedCache<P, F> {
+    version: u32,
+    /// Left out when empty: `files = []` would clash with `[[files]]` tables appended later.
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    files: Vec<SavedFile<P, F>>,
+}
+
+/// Tags of every file read so far, keyed by canonical path, so they are parsed again only when the file changes.
+struct MetadataCache {
+    files: HashMap<PathBuf, CachedFile>,
+    /// True if the file on disk is of this version, so new entries can be appended to it.
+    on_disk: bool,
+    /// Paths read since the cache was last written.
+    unsaved: Vec<PathBuf>,
+}
+
+/// Reads a saved cache, or returns None if it is unreadable or of another version.
+fn parse_cache(content: &str) -> Option<HashMap<PathBuf, CachedFile>> {
+    toml::from_str::<SavedCache<PathBuf, CachedFile>>(content)
+        .ok()
+        .filter(|saved| saved.version == CACHE_VERSION)
+        .map(|saved| {
+            saved
+                .files
+                .into_iter()
+                .map(|saved| (saved.path, saved.file))
+                .collect()
+        })
+}
+
+/// Writes entries as they appear in metadata.toml, as a whole file if `header` is set.
+/// TOML can't hold paths that aren't valid UTF-8, so those are left out.
+fn cache_toml<'a>(
+    files: impl Iterator<Item = (&'a PathBuf, &'a CachedFile)>,
+    header: bool,
+) -> io::Result<String> {
+    let saved = SavedCache {
+        version: CACHE_VERSION,
+        files: files
+            .filter(|(path, _)| path.to_str().is_some())
+            .map(|(path, file)| SavedFile { path, file })
+            .collect(),
+    };
+    let content = toml::to_string(&saved).map_err(io::Error::other)?;
+    match header {
+        true => Ok(content),
+        // Only the `[[files]]` tables after the version line.
+        false => Ok(content
+            .split_once('\n')
+            .map(|(_, files)| files.trim_start().to_string())
+            .unwrap_or_default()),
+    }
+}
+
+/// The cache shared by everything that reads tags; it is read from disk on first use.
+static CACHE: LazyLock<Mutex<MetadataCache>> = LazyLock::new(|| {
+    let files = data_file(CACHE_FILE)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| parse_cache(&content));
+    Mutex::new(MetadataCache {
+        on_disk: files.is_some(),
+        files: files.unwrap_or_default(),
+        unsaved: Vec::new(),
+    })
+});
+
+fn cache() -> MutexGuard<'static, MetadataCache> {
+    // A panic while holding the lock leaves the map intact, so a poisoned lock is still usable.
+    CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns the canonical path of a file with its modification time and size, or None if it can't be read.
+fn file_key(path: &Path) -> Option<(PathBuf, (u64, u64))> {
+    let canonical = fs::canonicalize(path).ok()?;
+    let stat = fs::metadata(&canonical).ok()?;
+    let mtime = stat
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos() as u64;
+    Some((canonical, (mtime, stat.len())))
+}
+
+/// Returns the file's metadata from the cache, calling `read` and caching the result
+/// if the file is new or changed since it was cached. `read` returns whether the length is an estimate;
+/// with `full_scan`, an estimated length is read again.
+pub fn cached_file_data(
+    path: &Path,
+    full_scan: bool,
+    read: impl FnOnce(&Path, bool) -> (FileMetadata, bool),
+) -> FileMetadata {
+    let Some((canonical, stamp)) = file_key(path) else {
+        return read(path, full_scan).0;
+    };
+    if let Some(cached) = cache().files.get(&canonical).filter(|cached| {
+        (cached.mtime_nanos, cached.size) == stamp && !(full_scan && cached.duration_estimated)
+    }) {
+        return cached.to_metadata(path);
+    }
+
+    // The lock isn't held while reading, which can take a while.
+    let (data, estimated) = read(path, full_scan);
+    let mut cache = cache();
+    cache.unsaved.push(canonical.clone());
+    cache
+        .files
+        .insert(canonical, CachedFile::of(&data, estimated, stamp));
+    data
+}
+
+/// Appends the files read since the last save to the cache on disk.
+/// The lock is only held to copy them, so this stays quick however large the cache is.
+pub fn save_metadata_cache() -> io::Result<()> {
+    let (entries, on_disk) = {
+        let mut cache = cache();
+        if cache.unsaved.is_empty() {
+            return Ok(());
+        }
+        let unsaved = std::mem::take(&mut cache.unsaved);
+        let entries: Vec<(PathBuf, CachedFile)> = unsaved
+            .into_iter()
+            .filter_map(|path| cache.files.get(&path).cloned().map(|file| (path, file)))
+            .collect();
+        (entries, cache.on_disk)
+    };
+    // A missing or outdated file can't be appended to.
+    if !on_disk {
+        return rewrite_cache(false);
+    }
+    let Some(path) = data_file(CACHE_FILE) else {
+        return Ok(());
+    };
+    let content = cache_toml(entries.iter().map(|(path, file)| (path, file)), false)?;
+    OpenOptions::new()
+        .append(true)
+        .open(path)?
+        .write_all(format!("\n{}", content).as_bytes())
+}
+
+/// Writes the whole cache anew, dropping files that no longer exist and entries appended more than once.
+/// Checking every file takes a while in a large library, so this is done on exit.
+pub fn compact_metadata_cache() -> io::Result<()> {
+    rewrite_cache(true)
+}
+
+/// Writes the whole cache anew, leaving out files that no longer exist if `prune` is set.
+fn rewrite_cache(prune: bool) -> io::Result<()> {
+    let files: Vec<(PathBuf, CachedFile)> = {
+        let mut cache = cache();
+        cache.unsaved.clear();
+        cache
+            .files
+            .iter()
+            .map(|(path, file)| (path.clone(), file.clone()))
+            .collect()
+    };
+    let content = cache_toml(
+        files
+            .iter()
+            .filter(|(path, _)| !prune || path.exists())
+            .map(|(path, file)| (path, file)),
+        true,
+    )?;
+    write_data_file(CACHE_FILE, &content)?;
+    cache().on_disk = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, fs::File, time::Duration};
+
+    fn cached(title: &str, mtime_nanos: u64) -> CachedFile {
+        let mut data = FileMetadata::with_file_only(Path::new("song.mp3"));
+        data.title = Some(title.to_string());
+        data.set_duration(Some(215.5));
+        CachedFile::of(&data, false, (mtime_nanos, 1234))
+    }
+
+    #[test]
+    fn appended_entries_replace_earlier_ones() {
+        let (a, b) = (PathBuf::from("/music/a.mp3"), PathBuf::from("/music/b.mp3"));
+        let whole = cache_toml(
+            [(&a, &cached("Old", 1)), (&b, &cached("B", 2))].into_iter(),
+            true,
+        );
+        let appended = cache_toml([(&a, &cached("New", 3))].into_iter(), false);
+        let content = format!("{}\n{}", whole.unwrap(), appended.unwrap());
+
+        let files = parse_cache(&content).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[&a].title.as_deref(), Some("New"));
+        assert_eq!(files[&a].mtime_nanos, 3);
+        assert_eq!(files[&a].duration_as_secs, Some(215.5));
+        assert_eq!(files[&b].title.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn entries_can_be_appended_to_an_empty_cache() {
+        let path = PathBuf::from("/music/a.mp3");
+        let empty = cache_toml(std::iter::empty(), true).unwrap();
+        let appended = cache_toml([(&path, &cached("A", 1))].into_iter(), false).unwrap();
+
+        assert!(parse_cache(&empty).unwrap().is_empty());
+        let files = parse_cache(&format!("{}\n{}", empty, appended)).unwrap();
+        assert_eq!(files[&path].title.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn other_versions_are_ignored() {
+        let content = cache_toml(std::iter::empty(), true).unwrap();
+        assert!(parse_cache(&content).is_some());
+        let old = content.replace(
+            &format!("version = {}", CACHE_VERSION),
+            &format!("version = {}", CACHE_VERSION - 1),
+        );
+        assert!(parse_cache(&old).is_none());
+    }
+
+    #[test]
+    fn changed_files_are_read_again() {
+        let dir = std::env::temp_dir().join(format!("rmpr-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("song.mp3");
+        fs::write(&path, b"one").unwrap();
+
+        let reads = Cell::new(0);
+        let read = |path: &Path, _| {
+            reads.set(reads.get() + 1);
+            (FileMetadata::with_file_only(path), false)
+        };
+        cached_file_data(&path, false, read);
+        cached_file_data(&path, false, read);
+        assert_eq!(reads.get(), 1);
+
+        // The same modification time but another size.
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, b"three").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        cached_file_data(&path, false, read);
+        assert_eq!(reads.get(), 2);
+
+        // The same size but another modification time.
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        cached_file_data(&path, false, read);
+        cached_file_data(&path, false, read);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(reads.get(), 3);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{data::metadata::file_metadata::FileMetadata, tui::app::PLAYABLE};
+use std::{
+    collections::HashMap,
+    fs::{self, read_dir},
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+/// CUE sheet times count frames, 75 to the second.
+const FRAMES_PER_SEC: f64 = 75.0;
+
+/// Virtual tracks keyed by the audio file they play from.
+pub type SheetTracks = Arc<HashMap<PathBuf, Vec<FileMetadata>>>;
+
+/// The parsed sheets of one directory, with the modification times they were parsed at.
+/// Adding, removing or renaming a sheet changes the directory's time; editing one changes its own.
+struct DirSheets {
+    modified: Option<SystemTime>,
+    sheets: Vec<(PathBuf, Option<SystemTime>)>,
+    tracks: SheetTracks,
+}
+
+/// Sheets parsed so far, keyed by directory, so a directory's sheets are parsed again only when they change.
+static SHEETS: LazyLock<Mutex<HashMap<PathBuf, DirSheets>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn sheets() -> MutexGuard<'static, HashMap<PathBuf, DirSheets>> {
+    SHEETS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|stat| stat.modified()).ok()
+}
+
+/// Where a CUE track lies within its file, in seconds; no end means it runs to the end of the file.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CueRange {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+/// One TRACK of a CUE sheet.
+struct CueTrack {
+    number: u16,
+    performer: Option<String>,
+    start: Option<f64>,
+    title: Option<String>,
+}
+
+/// One FILE of a CUE sheet and the tracks in it.
+struct CueFile {
+    path: PathBuf,
+    tracks: Vec<CueTrack>,
+}
+
+/// A parsed CUE sheet.
+struct CueSheet {
+    files: Vec<CueFile>,
+    performer: Option<String>,
+    title: Option<String>,
+}
+
+/// Splits a CUE line into its command and arguments, keeping quoted arguments whole.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            tokens.push(chars.by_ref().take_while(|&c| c != '"').collect());
+        } else {
+            let mut token = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                token.push(c);
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// Parses a "mm:ss:ff" CUE time into seconds.
+fn parse_time(time: &str) -> Option<f64> {
+    let mut parts = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some((minutes * 60 + seconds) as f64 + frames as f64 / FRAMES_PER_SEC)
+}
+
+/// Resolves a FILE name against the sheet's directory.
+/// Rips are often re-encoded after the sheet was written, so a missing file falls back to
+/// a playable file with the same stem ("album.wav" finding "album.flac").
+fn resolve_file(name: &str, base: &Path) -> PathBuf {
+    let path = base.join(name.replace('\\', "/"));
+    if path.exists() {
+        return path;
+    }
+    PLAYABLE
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|candidate| candidate.exists())
+        .unwrap_or(path)
+}
+
+/// Parses a CUE sheet; FILE names resolve against `base`.
+fn parse_cue(content: &str, base: &Path) -> CueSheet {
+    let mut sheet = CueSheet {
+        files: Vec::new(),
+        performer: None,
+        title: None,
+    };
+
+    for line in content.trim_start_matches('\u{feff}').lines() {
+        let tokens = tokenize(line);
+        let Some(command) = tokens.first() else {
+            continue;
+        };
+        let argument = tokens.get(1).cloned();
+        let track = sheet
+            .files
+            .last_mut()
+            .and_then(|file| file.tracks.last_mut());
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                if let Some(name) = argument {
+                    sheet.files.push(CueFile {
+                        path: resolve_file(&name, base),
+                        tracks: Vec::new(),
+                    });
+                }
+            }
+            "TRACK" => {
+                if let Some(file) = sheet.files.last_mut() {
+                    file.tracks.push(CueTrack {
+                        number: argument.and_then(|n| n.parse().ok()).unwrap_or(0),
+                        performer: None,
+                        start: None,
+                        title: None,
+                    });
+                }
+            }
+            "TITLE" => match track {
+                Some(track) => track.title = argument,
+                None => sheet.title = argument,
+            },
+            "PERFORMER" => match track {
+                Some(track) => track.performer = argument,
+                None => sheet.performer = argument,
+            },
+            // INDEX 01 is where the track starts; INDEX 00 marks the pregap before it.
+            "INDEX" if argument.as_deref() == Some("01") => {
+                if let Some(track) = track {
+                    track.start = tokens.get(2).and_then(|time| parse_time(time));
+                }
+            }
+            _ => {}
+        }
+    }
+    sheet
+}
+
+/// Turns a sheet into virtual tracks, keyed by the audio file they play from.
+/// Each track runs until the next one starts; tags of the audio file fill in what the sheet lacks.
+fn sheet_tracks(sheet: CueSheet) -> HashMap<PathBuf, Vec<FileMetadata>> {
+    let mut files = HashMap::new();
+    for file in sheet.files {
+        let base = FileMetadata::get_file_data(&file.path);
+        let starts: Vec<(&CueTrack, f64)> = file
+            .tracks
+            .iter()
+            .filter_map(|track| track.start.map(|start| (track, start)))
+            .collect();
+
+        let tracks: Vec<FileMetadata> = starts
+            .iter()
+            .enumerate()
+            .map(|(index, &(track, start))| {
+                let end = starts.get(index + 1).map(|&(_, end)| end);
+                let mut data = base.clone();
+                data.title = track.title.clone().or(data.title);
+                data.artist = track
+                    .performer
+                    .clone()
+                    .or(sheet.performer.clone())
+                    .or(data.artist);
+                data.album = data.album.or(sheet.title.clone());
+                data.track_number = Some(track.number);
+                data.set_duration(end.or(base.duration_as_secs).map(|end| end - start));
+                data.cue = Some(CueRange { start, end });
+                data
+            })
+            .collect();
+        if !tracks.is_empty() {
+            files.insert(file.path, tracks);
+        }
+    }
+    files
+}
+
+/// Reads every CUE sheet in `dir` into virtual tracks, keyed by the audio file they play from.
+/// Sheets are parsed once and kept until the directory or one of them changes.
+pub fn cue_sheets_in(dir: &Path) -> SheetTracks {
+    let dir_modified = modified(dir);
+    if let Some(cached) = sheets().get(dir)
+        && cached.modified == dir_modified
+        && cached
+            .sheets
+            .iter()
+            .all(|(sheet, sheet_modified)| modified(sheet) == *sheet_modified)
+    {
+        return Arc::clone(&cached.tracks);
+    }
+
+    let Ok(entries) = read_dir(dir) else {
+        return SheetTracks::default();
+    };
+    let sheet_paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+        })
+        .collect();
+    // The times are taken before reading, so a sheet changed while parsing is parsed again next time.
+    let sheets_modified = sheet_paths
+        .iter()
+        .map(|path| (path.clone(), modified(path)))
+        .collect();
+    let tracks: SheetTracks = Arc::new(
+        sheet_paths
+            .iter()
+            .filter_map(|path| fs::read(path).ok())
+            .flat_map(|bytes| sheet_tracks(parse_cue(&String::from_utf8_lossy(&bytes), dir)))
+            .collect(),
+    );
+
+    sheets().insert(
+        dir.to_path_buf(),
+        DirSheets {
+            modified: dir_modified,
+            sheets: sheets_modified,
+            tracks: Arc::clone(&tracks),
+        },
+    );
+    tracks
+}
+
+/// Reads a CUE sheet embedded in a FLAC file's CUESHEET comment.
+/// Embedded sheets always describe the file they are in, whatever their FILE line says.
+pub fn embedded_cue(path: &Path) -> Option<Vec<FileMetadata>> {
+    if !path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"))
+    {
+        return None;
+    }
+    let tag = metaflac::Tag::read_from_path(path).ok()?;
+    let content = tag.get_vorbis("CUESHEET")?.next()?.to_string();
+
+    let mut sheet = parse_cue(&content, path.parent()?);
+    let tracks = sheet.files.drain(..).flat_map(|file| file.tracks).collect();
+    sheet.files.push(CueFile {
+        path: path.to_path_buf(),
+        tracks,
+    });
+    sheet_tracks(sheet).remove(path)
+}
+
+/// Returns the CUE tracks of an audio file, from a sheet next to it or embedded in it.
+pub fn cue_tracks(path: &Path) -> Option<Vec<FileMetadata>> {
+    path.parent()
+        .and_then(|dir| cue_sheets_in(dir).get(path).cloned())
+        .or_else(|| embedded_cue(path))
+}
+
+/// Returns the tracks the audio files play, in order: their CUE tracks, or the files themselves.
+/// The sheets of each directory are looked up once, however many of its files are asked about.
+pub fn tracks_in_files(paths: &[PathBuf]) -> Vec<FileMetadata> {
+    let mut sheets: HashMap<&Path, SheetTracks> = HashMap::new();
+    paths
+        .iter()
+        .flat_map(|path| {
+            let in_sheet = path.parent().and_then(|dir| {
+                sheets
+                    .entry(dir)
+                    .or_insert_with(|| cue_sheets_in(dir))
+                    .get(path)
+                    .cloned()
+            });
+            in_sheet
+                .or_else(|| embedded_cue(path))
+                .unwrap_or_else(|| vec![FileMetadata::get_file_data(path)])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, time::Duration};
+
+    const SHEET: &str = "TITLE \"Album\"\nFILE \"album.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"One\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"Two\"\n    INDEX 01 01:30:00\n";
+
+    #[test]
+    fn tracks_run_until_the_next_one_starts() {
+        let sheet = parse_cue(SHEET, Path::new("/music"));
+        let tracks = sheet_tracks(sheet).remove(Path::new("/music/album.flac"));
+
+        let tracks = tracks.unwrap();
+        assert_eq!(tracks[0].title.as_deref(), Some("One"));
+        assert_eq!(tracks[0].album.as_deref(), Some("Album"));
+        assert_eq!(
+            tracks[0].cue,
+            Some(CueRange {
+                start: 0.0,
+                end: Some(90.0)
+            })
+        );
+        assert_eq!(tracks[1].cue.map(|cue| cue.end), Some(None));
+    }
+
+    #[test]
+    fn edited_sheets_are_parsed_again() {
+        let dir = std::env::temp_dir().join(format!("rmpr-cue-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sheet = dir.join("album.cue");
+        let audio = dir.join("album.flac");
+        fs::write(&sheet, SHEET).unwrap();
+
+        let title = |tracks: &SheetTracks| tracks[&audio][1].title.clone();
+        let first = cue_sheets_in(&dir);
+        assert_eq!(title(&first), Some("Two".to_string()));
+        assert!(Arc::ptr_eq(&first, &cue_sheets_in(&dir)));
+
+        fs::write(&sheet, SHEET.replace("Two", "Deux")).unwrap();
+        // Pushed forward explicitly, as the rewrite may land within the clock's granularity.
+        let later = modified(&sheet).unwrap() + Duration::from_secs(1);
+        File::options()
+            .write(true)
+            .open(&sheet)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let second = cue_sheets_in(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(title(&second), Some("Deux".to_string()));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::data::metadata::stream_info::probe;
+use std::{fs, path::Path};
+use symphonia::core::{codecs::CodecParameters, formats::FormatReader, units::TimeBase};
+
+/// How many packets an estimated length is extrapolated from.
+const ESTIMATE_PACKETS: usize = 200;
+
+/// A file's length in seconds, and whether it was only estimated from the start of the file.
+pub struct ProbedDuration {
+    pub secs: f64,
+    pub estimated: bool,
+}
+
+/// Converts a number of frames to seconds, using the sample rate if there is no time base.
+fn frames_to_secs(params: &CodecParameters, frames: u64) -> Option<f64> {
+    let time_base = params
+        .time_base
+        .or_else(|| params.sample_rate.map(|rate| TimeBase::new(1, rate)))?;
+    let time = time_base.calc_time(frames);
+    Some(time.seconds as f64 + time.frac)
+}
+
+/// Adds up the length of every packet of the track; slow, but right when the headers say nothing.
+fn scan_packets(format: &mut dyn FormatReader, track_id: u32) -> u64 {
+    let mut frames = 0;
+    while let Ok(packet) = format.next_packet() {
+        if packet.track_id() == track_id {
+            frames += packet.dur();
+        }
+    }
+    frames
+}
+
+/// Extrapolates the track's length from the frames per byte of its first packets, returning
+/// the frames and whether they are an estimate; a file that ends within them is counted exactly.
+fn estimate_packets(format: &mut dyn FormatReader, track_id: u32, file_size: u64) -> (u64, bool) {
+    let (mut frames, mut bytes, mut packets) = (0, 0, 0);
+    while packets < ESTIMATE_PACKETS {
+        let Ok(packet) = format.next_packet() else {
+            return (frames, false);
+        };
+        if packet.track_id() == track_id {
+            frames += packet.dur();
+            bytes += packet.buf().len() as u64;
+            packets += 1;
+        }
+    }
+    match bytes {
+        0 => (frames, true),
+        bytes => (
+            (frames as f64 * file_size as f64 / bytes as f64) as u64,
+            true,
+        ),
+    }
+}
+
+/// Reads a file's length from its container headers (or Xing/VBRI frames for MP3).
+/// If they don't have it, the whole file is scanned with `full_scan`, otherwise the length is estimated.
+pub fn probe_duration(path: &Path, full_scan: bool) -> Option<ProbedDuration> {
+    let mut format = probe(path)?.format;
+    let track = format.default_track()?;
+    let (track_id, params) = (track.id, track.codec_params.clone());
+
+    let (frames, estimated) = match params.n_frames {
+        Some(frames) => (frames, false),
+        None if full_scan => (scan_packets(format.as_mut(), track_id), false),
+        None => {
+            let file_size = fs::metadata(path).map(|stat| stat.len()).unwrap_or(0);
+            estimate_packets(format.as_mut(), track_id, file_size)
+        }
+    };
+    frames_to_secs(&params, frames)
+        .filter(|&secs| secs > 0.0)
+        .map(|secs| ProbedDuration { secs, estimated })
+}
@@ -0,0 +1,96 @@
+use audiotags::{AudioTag, FlacTag, Id3v2Tag, Mp4Tag};
+use id3::TagLike;
+use mp4ameta::FreeformIdent;
+
+/// Tags audiotags doesn't expose, read from the format's own tag.
+#[derive(Default)]
+pub struct ExtendedTags {
+    pub bpm: Option<u16>,
+    pub musicbrainz_album_id: Option<String>,
+    pub musicbrainz_artist_id: Option<String>,
+    pub musicbrainz_track_id: Option<String>,
+    pub original_date: Option<String>,
+    pub release_date: Option<String>,
+}
+
+/// Parses a BPM tag, which some taggers write with decimals.
+fn parse_bpm(text: &str) -> Option<u16> {
+    text.trim()
+        .parse::<f64>()
+        .ok()
+        .map(|bpm| bpm.round() as u16)
+}
+
+fn from_vorbis(tag: &metaflac::Tag) -> ExtendedTags {
+    let get = |key: &str| {
+        tag.get_vorbis(key)
+            .and_then(|mut values| values.next().map(str::to_string))
+    };
+    ExtendedTags {
+        bpm: get("BPM").as_deref().and_then(parse_bpm),
+        musicbrainz_album_id: get("MUSICBRAINZ_ALBUMID"),
+        musicbrainz_artist_id: get("MUSICBRAINZ_ARTISTID"),
+        musicbrainz_track_id: get("MUSICBRAINZ_TRACKID"),
+        original_date: get("ORIGINALDATE").or_else(|| get("ORIGINALYEAR")),
+        release_date: get("DATE"),
+    }
+}
+
+/// MusicBrainz IDs live in TXXX frames, except the recording ID, which is a UFID frame.
+fn from_id3(tag: &id3::Tag) -> ExtendedTags {
+    let extended = |description: &str| {
+        tag.extended_texts()
+            .find(|text| text.description.eq_ignore_ascii_case(description))
+            .map(|text| text.value.clone())
+    };
+    ExtendedTags {
+        bpm: tag
+            .get("TBPM")
+            .and_then(|frame| frame.content().text())
+            .and_then(parse_bpm),
+        musicbrainz_album_id: extended("MusicBrainz Album Id"),
+        musicbrainz_artist_id: extended("MusicBrainz Artist Id"),
+        musicbrainz_track_id: tag
+            .unique_file_identifiers()
+            .find(|ufid| ufid.owner_identifier == "http://musicbrainz.org")
+            .map(|ufid| String::from_utf8_lossy(&ufid.identifier).to_string()),
+        original_date: tag
+            .original_date_released()
+            .map(|date| date.to_string())
+            .or_else(|| extended("ORIGINALYEAR")),
+        release_date: tag
+            .date_released()
+            .or_else(|| tag.date_recorded())
+            .map(|date| date.to_string()),
+    }
+}
+
+fn from_mp4(tag: &mp4ameta::Tag) -> ExtendedTags {
+    let freeform = |name: &str| {
+        tag.strings_of(&FreeformIdent::new("com.apple.iTunes", name))
+            .next()
+            .map(str::to_string)
+    };
+    ExtendedTags {
+        bpm: tag.bpm(),
+        musicbrainz_album_id: freeform("MusicBrainz Album Id"),
+        musicbrainz_artist_id: freeform("MusicBrainz Artist Id"),
+        musicbrainz_track_id: freeform("MusicBrainz Track Id"),
+        original_date: freeform("ORIGINALDATE"),
+        release_date: tag.year().map(str::to_string),
+    }
+}
+
+/// Reads the extended tags out of a tag read by audiotags; formats without them (WAV) get none.
+pub fn read_extended(tags: Box<dyn AudioTag + Send + Sync>) -> ExtendedTags {
+    let any = tags.to_any();
+    if any.is::<FlacTag>() {
+        from_vorbis(&metaflac::Tag::from(tags))
+    } else if any.is::<Id3v2Tag>() {
+        from_id3(&id3::Tag::from(tags))
+    } else if any.is::<Mp4Tag>() {
+        from_mp4(&mp4ameta::Tag::from(tags))
+    } else {
+        ExtendedTags::default()
+    }
+}
@@ -1,18 +1,45 @@
-use crate::tui::app::PLAYABLE;
+use crate::{
+    data::metadata::{
+        cache::cached_file_data, cue::CueRange, duration::probe_duration,
+        extended_tags::read_extended,
+    },
+    tui::app::PLAYABLE,
+};
 use audiotags::Tag;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Identifies a track: its file and, for CUE tracks, where in the file it starts.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TrackId {
+    pub path: PathBuf,
+    pub start_millis: Option<u64>,
+}
 
 /// Encapsulates file data information.
 #[derive(Clone)]
 pub struct FileMetadata {
     pub album: Option<String>,
+    pub album_artist: Option<String>,
     pub artist: Option<String>,
+    pub bpm: Option<u16>,
+    pub comment: Option<String>,
+    pub composer: Option<String>,
+    pub cue: Option<CueRange>,
+    pub disc_number: Option<u16>,
+    pub disc_total: Option<u16>,
     pub duration_as_secs: Option<f64>,
     pub duration_display: Option<(f64, f64)>,
     pub file_path: PathBuf,
+    pub genre: Option<String>,
+    pub musicbrainz_album_id: Option<String>,
+    pub musicbrainz_artist_id: Option<String>,
+    pub musicbrainz_track_id: Option<String>,
+    pub original_date: Option<String>,
     pub raw_file: String,
+    pub release_date: Option<String>,
     pub title: Option<String>,
     pub track_number: Option<u16>,
+    pub track_total: Option<u16>,
     pub year: Option<i32>,
 }
 
@@ -20,72 +47,140 @@ impl FileMetadata {
     pub fn new() -> Self {
         Self {
             album: None,
+            album_artist: None,
             artist: None,
+            bpm: None,
+            comment: None,
+            composer: None,
+            cue: None,
+            disc_number: None,
+            disc_total: None,
             duration_as_secs: None,
             duration_display: None,
             file_path: PathBuf::new(),
+            genre: None,
+            musicbrainz_album_id: None,
+            musicbrainz_artist_id: None,
+            musicbrainz_track_id: None,
+            original_date: None,
             raw_file: String::new(),
+            release_date: None,
             title: None,
             track_number: None,
+            track_total: None,
             year: None,
         }
     }
 
     /// Returns the file name or "Unknown"
-    fn get_file_name(path: &PathBuf) -> String {
+    fn get_file_name(path: &Path) -> String {
         path.file_name()
             .map(|n| n.to_string_lossy().to_string())
             .unwrap_or("Unknown".to_string())
     }
 
     /// fn with_file_only(raw_file: String) -> Self {
-    fn with_file_only(path: &PathBuf) -> Self {
+    pub fn with_file_only(path: &Path) -> Self {
         Self {
-            album: None,
-            artist: None,
-            duration_as_secs: None,
-            duration_display: None,
-            file_path: path.clone(),
+            file_path: path.to_path_buf(),
             raw_file: Self::get_file_name(path),
-            title: None,
-            track_number: None,
-            year: None,
+            ..Self::new()
         }
     }
 
     /// Sets FileMetadata with the respective values from the file.
-    pub fn get_file_data(path: &PathBuf) -> FileMetadata {
-        let file_only = Self::with_file_only(path);
+    /// Tags come from the metadata cache unless the file changed since they were read.
+    /// A length the headers don't give is estimated; `load_file_data` finds it exactly.
+    pub fn get_file_data(path: &Path) -> FileMetadata {
+        Self::file_data(path, false)
+    }
+
+    /// Like `get_file_data`, but scans the whole file for a length the headers don't give.
+    /// That can take a while, so this is for background threads.
+    pub fn load_file_data(path: &Path) -> FileMetadata {
+        Self::file_data(path, true)
+    }
 
+    fn file_data(path: &Path, full_scan: bool) -> FileMetadata {
         let Some(ext) = path.extension() else {
-            return file_only;
+            return Self::with_file_only(path);
         };
 
         if !PLAYABLE.contains(&ext.to_string_lossy().to_ascii_lowercase().as_str()) {
-            return file_only;
+            return Self::with_file_only(path);
         }
 
+        cached_file_data(path, full_scan, Self::read_file_data)
+    }
+
+    /// Reads a playable file's tags from disk, asking the decoder for the duration if the tags lack it.
+    /// Also returns whether the duration is only an estimate.
+    fn read_file_data(path: &Path, full_scan: bool) -> (FileMetadata, bool) {
+        let mut data = Self::read_tags(path);
+        if data.duration_as_secs.is_some() {
+            return (data, false);
+        }
+        let probed = probe_duration(path, full_scan);
+        data.set_duration(probed.as_ref().map(|probed| probed.secs));
+        (data, probed.is_some_and(|probed| probed.estimated))
+    }
+
+    /// Reads a playable file's tags.
+    fn read_tags(path: &Path) -> FileMetadata {
+        let file_only = Self::with_file_only(path);
+
         let Ok(tags) = Tag::default().read_from_path(path) else {
             return file_only;
         };
 
-        Self {
+        let mut data = Self {
             album: tags.album_title().map(|n| n.to_string()),
+            album_artist: tags.album_artist().map(|n| n.to_string()),
             artist: tags.artist().map(|n| n.to_string()),
+            comment: tags.comment().map(|n| n.to_string()),
+            composer: tags.composer().map(|n| n.to_string()),
+            disc_number: tags.disc_number(),
+            disc_total: tags.total_discs(),
             duration_as_secs: tags.duration(),
             duration_display: tags.duration().map(Self::sec_to_min_sec),
-            file_path: path.clone(),
+            file_path: path.to_path_buf(),
+            genre: tags.genre().map(|n| n.to_string()),
             raw_file: Self::get_file_name(path),
             title: tags.title().map(|n| n.to_string()),
             track_number: tags.track_number(),
+            track_total: tags.total_tracks(),
             year: tags.year(),
+            ..Self::new()
+        };
+
+        let extended = read_extended(tags);
+        data.bpm = extended.bpm;
+        data.musicbrainz_album_id = extended.musicbrainz_album_id;
+        data.musicbrainz_artist_id = extended.musicbrainz_artist_id;
+        data.musicbrainz_track_id = extended.musicbrainz_track_id;
+        data.original_date = extended.original_date;
+        data.release_date = extended.release_date;
+        data
+    }
+
+    /// Returns what identifies this track; CUE tracks of one file differ by their start.
+    pub fn id(&self) -> TrackId {
+        TrackId {
+            path: self.file_path.clone(),
+            start_millis: self.cue.map(|cue| (cue.start * 1000.0) as u64),
         }
     }
 
+    /// Sets the duration, e.g. from a playlist when the tags don't have one.
+    pub fn set_duration(&mut self, duration: Option<f64>) {
+        self.duration_as_secs = duration;
+        self.duration_display = duration.map(Self::sec_to_min_sec);
+    }
+
     /// Display album or nothing.
     pub fn display_album(&self) -> String {
         match &self.album {
-            Some(display) => format!("{}", display),
+            Some(display) => display.clone(),
             None => String::new(),
         }
     }
@@ -93,7 +188,7 @@ impl FileMetadata {
     /// Display artists or nothing.
     pub fn display_artist(&self) -> String {
         match &self.artist {
-            Some(artist) => format!("{}", artist),
+            Some(artist) => artist.clone(),
             None => String::new(),
         }
     }
@@ -101,8 +196,8 @@ impl FileMetadata {
     /// Display title, or raw file, or nothing if neither is found.
     pub fn display_title(&self) -> String {
         match &self.title {
-            Some(title) => format!("{}", title),
-            None => format!("{}", self.raw_file),
+            Some(title) => title.clone(),
+            None => self.raw_file.clone(),
         }
     }
 
@@ -122,6 +217,109 @@ impl FileMetadata {
         }
     }
 
+    /// Display album artist or nothing.
+    pub fn display_album_artist(&self) -> String {
+        self.album_artist.clone().unwrap_or_default()
+    }
+
+    /// Display bpm, e.g. "120 bpm", or nothing.
+    pub fn display_bpm(&self) -> String {
+        match self.bpm {
+            Some(bpm) => format!("{} bpm", bpm),
+            None => String::new(),
+        }
+    }
+
+    /// Display comment or nothing.
+    pub fn display_comment(&self) -> String {
+        self.comment.clone().unwrap_or_default()
+    }
+
+    /// Display composer or nothing.
+    pub fn display_composer(&self) -> String {
+        self.composer.clone().unwrap_or_default()
+    }
+
+    /// Display disc_number, with the total if known ("1/2"), or nothing.
+    pub fn display_disc_number(&self) -> String {
+        match (self.disc_number, self.disc_total) {
+            (Some(disc), Some(total)) => format!("{}/{}", disc, total),
+            (Some(disc), None) => format!("{}", disc),
+            _ => String::new(),
+        }
+    }
+
+    /// Display genre or nothing.
+    pub fn display_genre(&self) -> String {
+        self.genre.clone().unwrap_or_default()
+    }
+
+    /// Display the MusicBrainz recording ID or nothing.
+    pub fn display_musicbrainz_track_id(&self) -> String {
+        self.musicbrainz_track_id.clone().unwrap_or_default()
+    }
+
+    /// Display the MusicBrainz release ID or nothing.
+    pub fn display_musicbrainz_album_id(&self) -> String {
+        self.musicbrainz_album_id.clone().unwrap_or_default()
+    }
+
+    /// Display the MusicBrainz artist ID or nothing.
+    pub fn display_musicbrainz_artist_id(&self) -> String {
+        self.musicbrainz_artist_id.clone().unwrap_or_default()
+    }
+
+    /// Display original_date or nothing.
+    pub fn display_original_date(&self) -> String {
+        self.original_date.clone().unwrap_or_default()
+    }
+
+    /// Display release_date or nothing.
+    pub fn display_release_date(&self) -> String {
+        self.release_date.clone().unwrap_or_default()
+    }
+
+    /// Display track_number with the total ("3/12"), or just the number, or nothing.
+    pub fn display_track_of_total(&self) -> String {
+        match (self.track_number, self.track_total) {
+            (Some(track), Some(total)) => format!("{}/{}", track, total),
+            _ => self.display_track_number(),
+        }
+    }
+
+    /// Display track_total or nothing.
+    pub fn display_track_total(&self) -> String {
+        match self.track_total {
+            Some(total) => format!("{}", total),
+            None => String::new(),
+        }
+    }
+
+    /// Display a field by its column name, or None for an unknown name.
+    pub fn display_field(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "album" => self.display_album(),
+            "album_artist" => self.display_album_artist(),
+            "artist" => self.display_artist(),
+            "bpm" => self.display_bpm(),
+            "comment" => self.display_comment(),
+            "composer" => self.display_composer(),
+            "disc" => self.display_disc_number(),
+            "duration" => self.display_duration_display(),
+            "genre" => self.display_genre(),
+            "musicbrainz_album_id" => self.display_musicbrainz_album_id(),
+            "musicbrainz_artist_id" => self.display_musicbrainz_artist_id(),
+            "musicbrainz_track_id" => self.display_musicbrainz_track_id(),
+            "original_date" => self.display_original_date(),
+            "release_date" => self.display_release_date(),
+            "title" => self.display_title(),
+            "track" => self.display_track_of_total(),
+            "track_total" => self.display_track_total(),
+            "year" => self.display_year(),
+            _ => return None,
+        })
+    }
+
     /// Converts seconds to seconds and minutes.
     fn sec_to_min_sec(duration: f64) -> (f64, f64) {
         let min = (duration / 60.0).floor();
@@ -129,6 +327,15 @@ impl FileMetadata {
         (min, sec)
     }
 
+    /// Formats seconds as "m:ss", or "h:mm:ss" from an hour up.
+    pub fn format_secs(duration: f64) -> String {
+        let total = duration.max(0.0).floor() as u64;
+        match total / 3600 {
+            0 => format!("{}:{:02}", total / 60, total % 60),
+            hours => format!("{}:{:02}:{:02}", hours, total / 60 % 60, total % 60),
+        }
+    }
+
     /// Display duration_display or nothing.
     pub fn display_duration_display(&self) -> String {
         match self.duration_display {
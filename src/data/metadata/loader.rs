@@ -0,0 +1,82 @@
+use crate::data::metadata::{cue::embedded_cue, file_metadata::FileMetadata};
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+/// How many threads read tags at once; reading is mostly waiting on the disk.
+const WORKERS: usize = 4;
+
+/// The tracks a file plays: its embedded CUE tracks, or the file itself.
+pub type Loaded = (PathBuf, Vec<FileMetadata>);
+
+/// Reads tags on worker threads so the UI never waits on the disk.
+/// Requests are answered in any order; `cancel_pending` drops the ones not started yet.
+pub struct MetadataLoader {
+    generation: Arc<AtomicUsize>,
+    jobs: Sender<(usize, PathBuf)>,
+    results: Receiver<Loaded>,
+}
+
+impl MetadataLoader {
+    pub fn new() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<(usize, PathBuf)>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let generation = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..WORKERS {
+            let job_receiver = Arc::clone(&job_receiver);
+            let generation = Arc::clone(&generation);
+            let result_sender = result_sender.clone();
+            thread::spawn(move || {
+                loop {
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok((job_generation, path)) = job else {
+                        return;
+                    };
+                    if job_generation != generation.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    // Workers can afford to scan a file whose headers don't give its length.
+                    let tracks = embedded_cue(&path)
+                        .unwrap_or_else(|| vec![FileMetadata::load_file_data(&path)]);
+                    if result_sender.send((path, tracks)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        Self {
+            generation,
+            jobs,
+            results,
+        }
+    }
+
+    /// Asks for a file's tracks to be read.
+    pub fn request(&self, path: PathBuf) {
+        let _ = self
+            .jobs
+            .send((self.generation.load(Ordering::Relaxed), path));
+    }
+
+    /// Drops the requests no worker has started on, e.g. after leaving a directory.
+    pub fn cancel_pending(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns what the workers finished since the last call, without waiting.
+    pub fn finished(&self) -> Vec<Loaded> {
+        self.results.try_iter().collect()
+    }
+}
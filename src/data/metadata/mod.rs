@@ -1 +1,7 @@
+pub mod cache;
+pub mod cue;
+pub mod duration;
+pub mod extended_tags;
 pub mod file_metadata;
+pub mod loader;
+pub mod stream_info;
@@ -0,0 +1,332 @@
+use std::{fs, fs::File, path::Path};
+use symphonia::core::{
+    audio::Channels,
+    codecs::CodecParameters,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::{Hint, ProbeResult},
+    units::TimeBase,
+};
+
+/// How many packets are compared to tell VBR from CBR.
+const BITRATE_SAMPLE_PACKETS: usize = 300;
+
+/// What a file's audio actually is, as opposed to what its tags say.
+#[derive(Clone)]
+pub struct StreamInfo {
+    pub bit_depth: Option<u32>,
+    /// Average over the audio of the whole file, leaving out tags and pictures, in kbit/s.
+    pub bitrate_kbps: Option<u32>,
+    pub channels: Option<Channels>,
+    pub codec: String,
+    pub container: String,
+    pub file_size: u64,
+    pub replay_gain: Vec<(&'static str, String)>,
+    pub sample_rate: Option<u32>,
+    /// Every tag frame as stored in the file, including embedded pictures.
+    pub tags: Vec<(String, String)>,
+    /// None for lossless codecs, where the bitrate always varies.
+    pub variable_bitrate: Option<bool>,
+}
+
+/// Opens a file with the decoder's format probe, hinted by its extension.
+pub fn probe(path: &Path) -> Option<ProbeResult> {
+    let file = File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()
+}
+
+/// Names the container by the file's extension; the probe doesn't say which reader it picked.
+fn container_name(path: &Path) -> String {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "flac" => "FLAC".to_string(),
+        "mp3" => "MPEG audio".to_string(),
+        "m4a" | "m4v" | "mp4" => "MP4".to_string(),
+        "wav" => "RIFF WAVE".to_string(),
+        _ => ext,
+    }
+}
+
+/// Labels the ReplayGain tags, whichever way the tag format spells them.
+fn replay_gain_label(key: &str, std_key: Option<StandardTagKey>) -> Option<&'static str> {
+    match std_key {
+        Some(StandardTagKey::ReplayGainTrackGain) => return Some("track gain"),
+        Some(StandardTagKey::ReplayGainTrackPeak) => return Some("track peak"),
+        Some(StandardTagKey::ReplayGainAlbumGain) => return Some("album gain"),
+        Some(StandardTagKey::ReplayGainAlbumPeak) => return Some("album peak"),
+        _ => {}
+    }
+    let key = key.to_ascii_uppercase();
+    [
+        ("REPLAYGAIN_TRACK_GAIN", "track gain"),
+        ("REPLAYGAIN_TRACK_PEAK", "track peak"),
+        ("REPLAYGAIN_ALBUM_GAIN", "album gain"),
+        ("REPLAYGAIN_ALBUM_PEAK", "album peak"),
+    ]
+    .into_iter()
+    .find(|(name, _)| key.ends_with(name))
+    .map(|(_, label)| label)
+}
+
+/// Reads packets of the track, up to `limit` of them, returning their summed size and length
+/// and the sizes of the first few.
+fn read_packets(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    limit: Option<usize>,
+) -> (u64, u64, Vec<usize>) {
+    let (mut bytes, mut frames, mut sizes) = (0, 0, Vec::new());
+    let mut read = 0;
+    while limit.is_none_or(|limit| read < limit)
+        && let Ok(packet) = format.next_packet()
+    {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        read += 1;
+        bytes += packet.buf().len() as u64;
+        frames += packet.dur();
+        if sizes.len() < BITRATE_SAMPLE_PACKETS && 0 < packet.dur() {
+            sizes.push(packet.buf().len());
+        }
+    }
+    (bytes, frames, sizes)
+}
+
+/// Returns the bitrate in kbit/s of `bytes` of audio lasting `frames`.
+fn bitrate_kbps(params: &CodecParameters, bytes: u64, frames: u64) -> Option<u32> {
+    let time_base = params
+        .time_base
+        .or_else(|| params.sample_rate.map(|rate| TimeBase::new(1, rate)));
+    time_base
+        .map(|time_base| time_base.calc_time(frames))
+        .map(|time| time.seconds as f64 + time.frac)
+        .filter(|&secs| 0.0 < secs)
+        .map(|secs| (bytes as f64 * 8.0 / secs / 1000.0).round() as u32)
+}
+
+impl StreamInfo {
+    /// Fills in what the stream's headers say; tags and the bitrate are left empty.
+    fn from_headers(path: &Path, params: &CodecParameters) -> StreamInfo {
+        let codec = symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|descriptor| descriptor.short_name.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        StreamInfo {
+            bit_depth: params.bits_per_sample,
+            bitrate_kbps: None,
+            channels: params.channels,
+            codec,
+            container: container_name(path),
+            file_size: fs::metadata(path).map(|stat| stat.len()).unwrap_or(0),
+            replay_gain: Vec::new(),
+            sample_rate: params.sample_rate,
+            tags: Vec::new(),
+            variable_bitrate: None,
+        }
+    }
+
+    /// Reads just enough for the badge: the codec, sample rate and bit depth from the headers,
+    /// and for lossy codecs the bitrate of the first packets. Tags are left out.
+    pub fn read_badge(path: &Path) -> Option<StreamInfo> {
+        let mut probed = probe(path)?;
+        let track = probed.format.default_track()?;
+        let (track_id, params) = (track.id, track.codec_params.clone());
+        let mut info = StreamInfo::from_headers(path, &params);
+        if params.bits_per_sample.is_none() {
+            let (bytes, frames, _) = read_packets(
+                probed.format.as_mut(),
+                track_id,
+                Some(BITRATE_SAMPLE_PACKETS),
+            );
+            info.bitrate_kbps = bitrate_kbps(&params, bytes, frames);
+        }
+        Some(info)
+    }
+
+    /// Reads a file's stream parameters and tags. Every packet is read for the bitrate, so this
+    /// takes a while for large files and belongs on a background thread.
+    pub fn read(path: &Path) -> Option<StreamInfo> {
+        let mut probed = probe(path)?;
+        let track = probed.format.default_track()?;
+        let (track_id, params) = (track.id, track.codec_params.clone());
+        let mut info = StreamInfo::from_headers(path, &params);
+
+        // Tags before the stream (ID3v2 in MP3s) come from the probe, the rest from the container.
+        let mut revisions: Vec<MetadataRevision> = Vec::new();
+        if let Some(revision) = probed
+            .metadata
+            .get()
+            .and_then(|meta| meta.current().cloned())
+        {
+            revisions.push(revision);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            revisions.push(revision.clone());
+        }
+        for revision in &revisions {
+            for tag in revision.tags() {
+                // RIFF INFO strings keep their terminating NUL.
+                let value = tag.value.to_string().trim_end_matches('\0').to_string();
+                if let Some(label) = replay_gain_label(&tag.key, tag.std_key) {
+                    info.replay_gain.push((label, value.clone()));
+                }
+                info.tags.push((tag.key.clone(), value));
+            }
+            for visual in revision.visuals() {
+                info.tags.push((
+                    "picture".to_string(),
+                    format!("{}, {} bytes", visual.media_type, visual.data.len()),
+                ));
+            }
+        }
+
+        // The audio's own size, so tags and pictures don't count towards the bitrate.
+        let (bytes, frames, sizes) = read_packets(probed.format.as_mut(), track_id, None);
+        info.bitrate_kbps = bitrate_kbps(&params, bytes, frames);
+
+        // Lossy frames of one size mean CBR; MP3 padding makes them differ by a byte at most.
+        info.variable_bitrate = match params.bits_per_sample {
+            Some(_) => None,
+            None => {
+                let smallest = sizes.iter().min();
+                let largest = sizes.iter().max();
+                smallest
+                    .zip(largest)
+                    .map(|(smallest, largest)| 1 < largest - smallest)
+            }
+        };
+        Some(info)
+    }
+
+    /// Returns the codec's short name for display, e.g. "FLAC"; all PCM variants are "PCM".
+    pub fn codec_name(&self) -> String {
+        match self.codec.starts_with("pcm") {
+            true => "PCM".to_string(),
+            false => self.codec.to_ascii_uppercase(),
+        }
+    }
+
+    /// Returns the sample rate in kHz, e.g. "44.1" or "96".
+    pub fn display_sample_rate(&self) -> String {
+        self.sample_rate
+            .map(|rate| format!("{}", rate as f64 / 1000.0))
+            .unwrap_or_default()
+    }
+
+    /// Describes the channel layout, e.g. "stereo" or "6 channels".
+    pub fn display_channels(&self) -> String {
+        match self.channels.map(Channels::count) {
+            None => String::new(),
+            Some(1) => "mono".to_string(),
+            Some(2) => "stereo".to_string(),
+            Some(count) => format!("{} channels", count),
+        }
+    }
+
+    /// Describes the average bitrate and whether it varies, e.g. "245 kbit/s VBR".
+    pub fn display_bitrate(&self) -> String {
+        let mode = match self.variable_bitrate {
+            Some(true) => " VBR",
+            Some(false) => " CBR",
+            None => "",
+        };
+        self.bitrate_kbps
+            .map(|kbps| format!("{} kbit/s{}", kbps, mode))
+            .unwrap_or_default()
+    }
+
+    /// Returns the file size in MB, e.g. "12.3 MB".
+    pub fn display_file_size(&self) -> String {
+        format!("{:.1} MB", self.file_size as f64 / 1_000_000.0)
+    }
+
+    /// A short description of the format: bit depth and sample rate for lossless
+    /// files (e.g. "FLAC 24/96"), the bitrate for lossy ones (e.g. "MP3 320k").
+    pub fn badge(&self) -> String {
+        match (self.bit_depth, self.bitrate_kbps) {
+            (Some(bits), _) if self.sample_rate.is_some() => {
+                format!(
+                    "{} {}/{}",
+                    self.codec_name(),
+                    bits,
+                    self.display_sample_rate()
+                )
+            }
+            (None, Some(kbps)) => format!("{} {}k", self.codec_name(), kbps),
+            _ => self.codec_name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a second of 16 bit stereo 44.1 kHz silence with a large title tag before the audio.
+    fn wav_with_tag() -> Vec<u8> {
+        let chunk = |id: &[u8], body: Vec<u8>| {
+            let mut chunk = id.to_vec();
+            chunk.extend((body.len() as u32).to_le_bytes());
+            chunk.extend(body);
+            chunk
+        };
+        let mut format = Vec::new();
+        format.extend(1u16.to_le_bytes());
+        format.extend(2u16.to_le_bytes());
+        format.extend(44_100u32.to_le_bytes());
+        format.extend((44_100u32 * 4).to_le_bytes());
+        format.extend(4u16.to_le_bytes());
+        format.extend(16u16.to_le_bytes());
+        let mut info = b"INFO".to_vec();
+        info.extend(chunk(b"INAM", vec![b'x'; 20_000]));
+
+        let mut body = b"WAVE".to_vec();
+        body.extend(chunk(b"fmt ", format));
+        body.extend(chunk(b"LIST", info));
+        body.extend(chunk(b"data", vec![0; 44_100 * 4]));
+        chunk(b"RIFF", body)
+    }
+
+    #[test]
+    fn bitrate_leaves_out_tags() {
+        let path = std::env::temp_dir().join(format!("rmpr-stream-{}.wav", std::process::id()));
+        fs::write(&path, wav_with_tag()).unwrap();
+        let info = StreamInfo::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        let info = info.unwrap();
+        assert_eq!(info.bitrate_kbps, Some(1411));
+        assert_eq!(info.badge(), "PCM 16/44.1");
+        assert!(info.tags.iter().any(|(_, value)| value.len() == 20_000));
+    }
+
+    #[test]
+    fn badge_comes_from_the_headers() {
+        let path = std::env::temp_dir().join(format!("rmpr-badge-{}.wav", std::process::id()));
+        fs::write(&path, wav_with_tag()).unwrap();
+        let info = StreamInfo::read_badge(&path);
+        fs::remove_file(&path).unwrap();
+
+        let info = info.unwrap();
+        assert_eq!(info.badge(), "PCM 16/44.1");
+        assert!(info.tags.is_empty());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod config;
+pub mod library;
 pub mod metadata;
+pub mod playlist;
 pub mod queue;
+pub mod session;
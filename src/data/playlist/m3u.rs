@@ -0,0 +1,114 @@
+use crate::data::{
+    metadata::file_metadata::FileMetadata,
+    playlist::{
+        PlaylistEntry, read_vlc_option, relative_entry_path, resolve_entry_path, vlc_options,
+    },
+};
+use std::path::Path;
+
+/// Parses an (extended) M3U playlist; relative paths resolve against `base`.
+/// `#EXTINF:<seconds>,<artist> - <title>` lines describe the entry that follows them; the artist is optional.
+/// `#EXTVLCOPT:start-time=`/`stop-time=` lines limit it to part of the file.
+pub fn parse_m3u(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let mut entries = Vec::new();
+    let mut info: Option<(Option<f64>, Option<String>, Option<String>)> = None;
+    let mut cue = None;
+
+    for line in content.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            let (duration, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let (artist, title) = match title.split_once(" - ") {
+                Some((artist, title)) => (Some(artist.trim().to_string()), title),
+                None => (None, title),
+            };
+            info = Some((
+                duration
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&duration: &f64| 0.0 <= duration),
+                artist.filter(|artist| !artist.is_empty()),
+                Some(title.trim().to_string()).filter(|title| !title.is_empty()),
+            ));
+            continue;
+        }
+        if let Some(option) = line.strip_prefix("#EXTVLCOPT:") {
+            read_vlc_option(option, &mut cue);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (duration, artist, title) = info.take().unwrap_or((None, None, None));
+        let cue = cue.take();
+        if let Some(path) = resolve_entry_path(line, base) {
+            entries.push(PlaylistEntry {
+                artist,
+                cue,
+                duration,
+                path,
+                title,
+                ..Default::default()
+            });
+        }
+    }
+    entries
+}
+
+/// Writes an extended M3U playlist that will be saved at `playlist`.
+pub fn write_m3u(items: &[FileMetadata], playlist: &Path) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    for item in items {
+        let title = match &item.artist {
+            Some(artist) => format!("{} - {}", artist, item.display_title()),
+            None => item.display_title(),
+        };
+        content.push_str(&format!(
+            "#EXTINF:{},{}\n",
+            item.duration_as_secs
+                .map(|duration| duration.round() as i64)
+                .unwrap_or(-1),
+            title
+        ));
+        for option in item.cue.iter().flat_map(vlc_options) {
+            content.push_str(&format!("#EXTVLCOPT:{}\n", option));
+        }
+        content.push_str(&format!(
+            "{}\n",
+            relative_entry_path(&item.file_path, playlist)
+        ));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::playlist::tests::fixtures;
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn parses_extinf_lines() {
+        let dir = fixtures();
+        let entries = parse_m3u(&fs::read_to_string(dir.join("mixed.m3u")).unwrap(), &dir);
+
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                dir.join("tagged.mp3"),
+                dir.join("Sub Dir/untagged.flac"),
+                PathBuf::from("/tmp/My Music/song#1.ogg"),
+            ]
+        );
+        assert_eq!(entries[0].title, None);
+        assert_eq!(entries[1].artist.as_deref(), Some("Playlist Artist"));
+        assert_eq!(entries[1].title.as_deref(), Some("Playlist Title"));
+        assert_eq!(entries[1].duration, Some(215.0));
+        assert_eq!(entries[2].artist, None);
+        assert_eq!(entries[2].title.as_deref(), Some("Absolute"));
+        assert_eq!(entries[2].duration, None);
+    }
+}
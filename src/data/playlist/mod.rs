@@ -0,0 +1,313 @@
+pub mod m3u;
+pub mod playlist_funcs;
+pub mod pls;
+pub mod repair;
+pub mod saved;
+pub mod smart;
+pub mod xspf;
+
+use crate::data::metadata::{cue::CueRange, file_metadata::FileMetadata};
+use std::path::{Path, PathBuf};
+
+/// One track read from a playlist file, with whatever the playlist says about it.
+#[derive(Default)]
+pub struct PlaylistEntry {
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub cue: Option<CueRange>,
+    pub duration: Option<f64>,
+    pub path: PathBuf,
+    pub title: Option<String>,
+}
+
+impl PlaylistEntry {
+    /// Takes what the playlist says about the track, filling in the gaps from its tags.
+    pub fn into_metadata(self) -> FileMetadata {
+        let mut data = FileMetadata::get_file_data(&self.path);
+        data.title = self.title.or(data.title);
+        data.artist = self.artist.or(data.artist);
+        data.album = self.album.or(data.album);
+        if let Some(cue) = self.cue {
+            let end = cue.end.or(data.duration_as_secs);
+            data.set_duration(end.map(|end| end - cue.start));
+            data.cue = Some(cue);
+        }
+        if self.duration.is_some() {
+            data.set_duration(self.duration);
+        }
+        data
+    }
+}
+
+/// Reads a VLC "start-time=<seconds>" or "stop-time=<seconds>" option into `range`.
+/// M3U and XSPF playlists use these to play part of a file, such as a CUE track.
+pub fn read_vlc_option(option: &str, range: &mut Option<CueRange>) {
+    let Some((name, value)) = option.trim().split_once('=') else {
+        return;
+    };
+    let Ok(value) = value.trim().parse::<f64>() else {
+        return;
+    };
+    let name = name.trim();
+    if name != "start-time" && name != "stop-time" {
+        return;
+    }
+    let range = range.get_or_insert(CueRange {
+        start: 0.0,
+        end: None,
+    });
+    match name {
+        "start-time" => range.start = value,
+        _ => range.end = Some(value),
+    }
+}
+
+/// The VLC options that play only `range` of a file.
+pub fn vlc_options(range: &CueRange) -> Vec<String> {
+    let mut options = vec![format!("start-time={}", range.start)];
+    if let Some(end) = range.end {
+        options.push(format!("stop-time={}", end));
+    }
+    options
+}
+
+/// Resolves a path written in a playlist against the playlist's directory.
+/// `file://` URIs (with or without a "localhost" host) and Windows separators in relative paths are understood; other URLs are skipped.
+pub fn resolve_entry_path(entry: &str, base: &Path) -> Option<PathBuf> {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return None;
+    }
+    if let Some(path) = entry.strip_prefix("file://") {
+        // "file://localhost/path" names the same file as "file:///path".
+        let path = path
+            .strip_prefix("localhost")
+            .filter(|rest| rest.starts_with('/'))
+            .unwrap_or(path);
+        return Some(PathBuf::from(percent_decode(path)));
+    }
+    if entry.contains("://") {
+        return None;
+    }
+
+    let path = PathBuf::from(entry);
+    match path.is_absolute() {
+        true => Some(path),
+        false => Some(base.join(entry.replace('\\', "/"))),
+    }
+}
+
+/// Returns the path to write into a playlist saved at `playlist`: relative if the track is beneath the playlist's directory.
+pub fn relative_entry_path(path: &Path, playlist: &Path) -> String {
+    playlist
+        .parent()
+        .and_then(|base| path.strip_prefix(base).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Decodes "%XX" escapes, as found in URIs.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Escapes everything but unreserved characters and '/' as "%XX", for writing paths as URIs.
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::playlist::{
+        m3u::{parse_m3u, write_m3u},
+        pls::{parse_pls, write_pls},
+        xspf::{parse_xspf, write_xspf},
+    };
+    use std::fs;
+
+    type Parse = fn(&str, &Path) -> Vec<PlaylistEntry>;
+    type Write = fn(&[FileMetadata], &Path) -> String;
+
+    /// The fixture playlist of each format, with its reader and writer.
+    const FORMATS: [(&str, Parse, Write); 3] = [
+        ("mixed.m3u", parse_m3u, write_m3u),
+        ("mixed.pls", parse_pls, write_pls),
+        ("mixed.xspf", parse_xspf, write_xspf),
+    ];
+
+    pub(super) fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("test-files/playlists")
+    }
+
+    fn read(content: &str, parse: Parse) -> Vec<FileMetadata> {
+        parse(content, &fixtures())
+            .into_iter()
+            .map(PlaylistEntry::into_metadata)
+            .collect()
+    }
+
+    /// Two CUE tracks of the tagged fixture; the second runs to the end of the file.
+    fn cue_items() -> Vec<FileMetadata> {
+        let ranges = [("Part One", 0.0, Some(61.5)), ("Part Two", 61.5, None)];
+        ranges
+            .into_iter()
+            .map(|(title, start, end)| {
+                let mut data = FileMetadata::get_file_data(&fixtures().join("tagged.mp3"));
+                data.title = Some(title.to_string());
+                data.set_duration(end.map(|end| end - start));
+                data.cue = Some(CueRange { start, end });
+                data
+            })
+            .collect()
+    }
+
+    fn summary(items: &[FileMetadata]) -> Vec<impl PartialEq + std::fmt::Debug> {
+        items
+            .iter()
+            .map(|item| {
+                (
+                    item.file_path.clone(),
+                    item.title.clone(),
+                    item.artist.clone(),
+                    item.album.clone(),
+                    item.duration_as_secs,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn entry_paths_resolve_against_the_playlist() {
+        let base = Path::new("/music/lists");
+        let cases = [
+            ("song.mp3", Some("/music/lists/song.mp3")),
+            ("Sub Dir\\song.mp3", Some("/music/lists/Sub Dir/song.mp3")),
+            ("/abs/song.mp3", Some("/abs/song.mp3")),
+            ("file:///abs/My%20Song.mp3", Some("/abs/My Song.mp3")),
+            ("file://localhost/abs/song%231.mp3", Some("/abs/song#1.mp3")),
+            ("http://example.com/stream.mp3", None),
+            ("  ", None),
+        ];
+        for (entry, expected) in cases {
+            assert_eq!(
+                resolve_entry_path(entry, base),
+                expected.map(PathBuf::from),
+                "{}",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn percent_encoding_round_trips() {
+        let path = "/Music/AC/DC & Friends/100% #1 ü.flac";
+        assert_eq!(
+            percent_encode(path),
+            "/Music/AC/DC%20%26%20Friends/100%25%20%231%20%C3%BC.flac"
+        );
+        assert_eq!(percent_decode(&percent_encode(path)), path);
+    }
+
+    #[test]
+    fn playlist_fields_win_over_tags() {
+        let dir = fixtures();
+        let entry = PlaylistEntry {
+            artist: Some("Playlist Artist".to_string()),
+            duration: Some(90.0),
+            path: dir.join("tagged.mp3"),
+            title: Some("Playlist Title".to_string()),
+            ..Default::default()
+        };
+        let data = entry.into_metadata();
+
+        assert_eq!(data.title.as_deref(), Some("Playlist Title"));
+        assert_eq!(data.artist.as_deref(), Some("Playlist Artist"));
+        assert_eq!(data.duration_as_secs, Some(90.0));
+        // The playlist has no album, so the tag's is kept.
+        assert_eq!(data.album.as_deref(), Some("Tagged Album"));
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_tags() {
+        for (name, parse, _) in FORMATS {
+            let items = read(&fs::read_to_string(fixtures().join(name)).unwrap(), parse);
+
+            assert_eq!(items[0].title.as_deref(), Some("Tagged Title"), "{}", name);
+            assert_eq!(
+                items[0].artist.as_deref(),
+                Some("Tagged Artist"),
+                "{}",
+                name
+            );
+            assert_eq!(items[0].album.as_deref(), Some("Tagged Album"), "{}", name);
+            // Without tags, the playlist's fields are used.
+            assert_eq!(
+                items[1].title.as_deref(),
+                Some("Playlist Title"),
+                "{}",
+                name
+            );
+            assert_eq!(items[1].duration_as_secs, Some(215.0), "{}", name);
+        }
+    }
+
+    #[test]
+    fn written_playlists_read_back_the_same() {
+        for (name, parse, write) in FORMATS {
+            let items = read(&fs::read_to_string(fixtures().join(name)).unwrap(), parse);
+            let reread = read(&write(&items, &fixtures().join("written")), parse);
+
+            assert_eq!(summary(&reread), summary(&items), "{}", name);
+        }
+    }
+
+    #[test]
+    fn cue_ranges_read_back_the_same() {
+        let items = cue_items();
+        for (name, parse, write) in FORMATS {
+            let reread = read(&write(&items, &fixtures().join("written")), parse);
+
+            let ranges = |items: &[FileMetadata]| {
+                items
+                    .iter()
+                    .map(|item| {
+                        (
+                            item.title.clone(),
+                            item.cue,
+                            item.duration_as_secs.map(f64::round),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(ranges(&reread), ranges(&items), "{}", name);
+        }
+    }
+}
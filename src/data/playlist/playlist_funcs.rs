@@ -0,0 +1,132 @@
+use crate::{
+    data::{
+        metadata::file_metadata::FileMetadata,
+        playlist::{
+            m3u::{parse_m3u, write_m3u},
+            pls::{parse_pls, write_pls},
+            xspf::{parse_xspf, write_xspf},
+        },
+        queue::play_queue::EnqueueMode,
+    },
+    tui::{
+        app::{App, PLAYLISTS},
+        popup::{InputAction, PendingAction, Popup},
+        render::fs_browser::FileBrowser,
+    },
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Returns true if the file is a playlist rmpr can open.
+pub fn is_playlist(path: &Path) -> bool {
+    FileBrowser::is_playable_file(path, &PLAYLISTS)
+}
+
+/// Returns the playlist's lowercased extension.
+fn playlist_format(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// Reads a playlist file into queue items, picking the format by extension.
+pub fn read_playlist(path: &Path) -> io::Result<Vec<FileMetadata>> {
+    let base = path.parent().unwrap_or(Path::new("/"));
+    let content = fs::read_to_string(path)?;
+    let entries = match playlist_format(path).as_str() {
+        "pls" => parse_pls(&content, base),
+        "xspf" => parse_xspf(&content, base),
+        _ => parse_m3u(&content, base),
+    };
+    Ok(entries
+        .into_iter()
+        .map(|entry| entry.into_metadata())
+        .collect())
+}
+
+/// Writes items to a playlist file, picking the format by extension.
+pub fn write_playlist_file(path: &Path, items: &[FileMetadata]) -> io::Result<()> {
+    let content = match playlist_format(path).as_str() {
+        "pls" => write_pls(items, path),
+        "xspf" => write_xspf(items, path),
+        _ => write_m3u(items, path),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+impl App {
+    /// Loads a playlist: `Play` replaces the queue with it, the other modes enqueue it.
+    /// If some of its files are gone, replacements are offered first.
+    pub fn load_playlist(&mut self, path: &Path, mode: EnqueueMode) {
+        let items = match read_playlist(path) {
+            Ok(items) if !items.is_empty() => items,
+            Ok(_) => return,
+            Err(e) => {
+                self.show_message(format!("Failed to read playlist: {}", e));
+                return;
+            }
+        };
+        if items.iter().any(|item| !item.file_path.exists()) {
+            self.start_repair(path.to_path_buf(), items, mode);
+        } else {
+            self.queue_playlist_items(items, mode);
+        }
+    }
+
+    /// Puts a playlist's items into the queue: `Play` replaces the queue, the other modes enqueue them.
+    pub fn queue_playlist_items(&mut self, items: Vec<FileMetadata>, mode: EnqueueMode) {
+        match mode {
+            EnqueueMode::Play => self.replace_queue(items),
+            _ => self.enqueue(items, mode),
+        }
+    }
+
+    /// Asks for the name to save the queue under.
+    pub fn handle_save_playlist(&mut self) {
+        if self.queue.len() == 0 {
+            return;
+        }
+        self.popup = Some(Popup::Input {
+            prompt: format!(
+                "Save queue to {} as:",
+                self.format_display_path(&self.config.directories.playlist_directory)
+            ),
+            input: self.queues[self.active_queue].name.clone(),
+            action: InputAction::SavePlaylist,
+        });
+    }
+
+    /// Saves the queue in the playlist directory, as `<name>.m3u8` unless the name has a playlist extension.
+    /// Asks first if that would overwrite a playlist.
+    pub fn save_playlist_as(&mut self, name: &str) {
+        let mut path = self.config.directories.playlist_directory.join(name);
+        if !is_playlist(&path) {
+            path = self
+                .config
+                .directories
+                .playlist_directory
+                .join(format!("{}.m3u8", name));
+        }
+
+        if path.exists() {
+            self.popup = Some(Popup::Confirm {
+                message: format!("Overwrite {}?", self.format_display_path(&path)),
+                action: PendingAction::SavePlaylist(path),
+            });
+        } else {
+            self.write_playlist(path);
+        }
+    }
+
+    /// Writes the queue to a playlist file.
+    pub fn write_playlist(&mut self, path: PathBuf) {
+        if let Err(e) = write_playlist_file(&path, &self.queue.items) {
+            self.show_message(format!("Failed to save playlist: {}", e));
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::data::{
+    metadata::{cue::CueRange, file_metadata::FileMetadata},
+    playlist::{PlaylistEntry, relative_entry_path, resolve_entry_path},
+};
+use std::{collections::BTreeMap, path::Path};
+
+/// Parses a PLS playlist; relative paths resolve against `base`.
+/// Entries are numbered `FileN`/`TitleN`/`LengthN` keys and are returned in number order.
+/// `StartN`/`StopN` keys, which only this player writes, limit an entry to part of its file.
+pub fn parse_pls(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let mut numbered: BTreeMap<usize, PlaylistEntry> = BTreeMap::new();
+
+    for line in content.trim_start_matches('\u{feff}').lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+        let Ok(number) = key[split..].parse::<usize>() else {
+            continue;
+        };
+        let entry = numbered.entry(number).or_default();
+
+        match &key[..split] {
+            "file" => {
+                if let Some(path) = resolve_entry_path(value, base) {
+                    entry.path = path;
+                }
+            }
+            "title" => entry.title = Some(value.to_string()).filter(|title| !title.is_empty()),
+            "length" => {
+                entry.duration = value.parse().ok().filter(|&duration: &f64| 0.0 <= duration)
+            }
+            "start" => {
+                if let Ok(start) = value.parse() {
+                    entry.cue.get_or_insert(CueRange { start, end: None }).start = start;
+                }
+            }
+            "stop" => {
+                if let Ok(end) = value.parse() {
+                    entry
+                        .cue
+                        .get_or_insert(CueRange {
+                            start: 0.0,
+                            end: None,
+                        })
+                        .end = Some(end);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    numbered
+        .into_values()
+        .filter(|entry| !entry.path.as_os_str().is_empty())
+        .collect()
+}
+
+/// Writes a PLS playlist that will be saved at `playlist`.
+pub fn write_pls(items: &[FileMetadata], playlist: &Path) -> String {
+    let mut content = String::from("[playlist]\n");
+    for (index, item) in items.iter().enumerate() {
+        let number = index + 1;
+        content.push_str(&format!(
+            "File{}={}\n",
+            number,
+            relative_entry_path(&item.file_path, playlist)
+        ));
+        if let Some(title) = &item.title {
+            content.push_str(&format!("Title{}={}\n", number, title));
+        }
+        content.push_str(&format!(
+            "Length{}={}\n",
+            number,
+            item.duration_as_secs
+                .map(|duration| duration.round() as i64)
+                .unwrap_or(-1)
+        ));
+        if let Some(cue) = &item.cue {
+            content.push_str(&format!("Start{}={}\n", number, cue.start));
+            if let Some(end) = cue.end {
+                content.push_str(&format!("Stop{}={}\n", number, end));
+            }
+        }
+    }
+    content.push_str(&format!("NumberOfEntries={}\nVersion=2\n", items.len()));
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::playlist::tests::fixtures;
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn parses_numbered_entries() {
+        let dir = fixtures();
+        let entries = parse_pls(&fs::read_to_string(dir.join("mixed.pls")).unwrap(), &dir);
+
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                dir.join("tagged.mp3"),
+                dir.join("Sub Dir/untagged.flac"),
+                PathBuf::from("/tmp/My Music/song#1.ogg"),
+            ]
+        );
+        assert_eq!(entries[0].title, None);
+        assert_eq!(entries[1].title.as_deref(), Some("Playlist Title"));
+        assert_eq!(entries[1].duration, Some(215.0));
+        // A length of -1 means unknown.
+        assert_eq!(entries[2].duration, None);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    data::{
+        library::scan::playable_files, metadata::file_metadata::FileMetadata,
+        playlist::playlist_funcs::write_playlist_file, queue::play_queue::EnqueueMode,
+    },
+    tui::{
+        app::{App, Tab},
+        popup::Popup,
+    },
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+/// How far apart two lengths may be to count as the same recording.
+const DURATION_TOLERANCE_SECS: f64 = 3.0;
+/// How far apart two lengths may be when nothing but the length matches.
+const DURATION_ONLY_TOLERANCE_SECS: f64 = 1.0;
+/// How many candidates are offered for one missing entry.
+const MAX_CANDIDATES: usize = 5;
+
+/// How the candidates for a missing playlist entry were found.
+#[derive(Clone, Copy)]
+pub enum MatchKind {
+    FileName,
+    Tags,
+    /// Neither name nor tags matched, only the length; never accepted without asking.
+    Duration,
+}
+
+impl MatchKind {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            MatchKind::FileName => "file name",
+            MatchKind::Tags => "tags",
+            MatchKind::Duration => "duration",
+        }
+    }
+}
+
+/// A missing playlist entry and the candidates found for it, closest in length first.
+pub struct Repair {
+    pub accepted: bool,
+    pub candidates: Vec<FileMetadata>,
+    pub chosen: usize,
+    pub index: usize,
+    pub kind: MatchKind,
+}
+
+impl Repair {
+    /// Returns the candidate that replaces the entry if the repair is accepted.
+    pub fn replacement(&self) -> Option<&FileMetadata> {
+        self.candidates.get(self.chosen)
+    }
+}
+
+/// What a repair search sends back: the library it walked and a repair per missing entry.
+pub type RepairSearch = (Vec<PathBuf>, Vec<Repair>);
+
+/// Lowercases text and drops everything but letters and digits, so "01 - Song.mp3" and "01_song" compare equal.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the normalized file name without its extension.
+fn normalized_stem(path: &Path) -> String {
+    normalize(
+        &path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    )
+}
+
+/// Returns how far a candidate's length is from the missing entry's, if both are known.
+fn length_off(candidate: &FileMetadata, missing: &FileMetadata) -> Option<f64> {
+    Some((candidate.duration_as_secs? - missing.duration_as_secs?).abs())
+}
+
+/// Orders candidates closest in length first, keeping at most `MAX_CANDIDATES`.
+/// A lone candidate, or a closest one within the tolerance, is accepted right away.
+fn rank(mut candidates: Vec<FileMetadata>, missing: &FileMetadata) -> (Vec<FileMetadata>, bool) {
+    candidates.sort_by(|a, b| {
+        let off = |candidate| length_off(candidate, missing).unwrap_or(f64::INFINITY);
+        off(a).total_cmp(&off(b))
+    });
+    candidates.truncate(MAX_CANDIDATES);
+    let accepted = candidates.len() == 1
+        || candidates
+            .first()
+            .and_then(|closest| length_off(closest, missing))
+            .is_some_and(|off| off <= DURATION_TOLERANCE_SECS);
+    (candidates, accepted)
+}
+
+/// Returns true if the candidate's tags match what the playlist said about the missing entry.
+/// M3U titles are often "Artist - Title", so that form counts as well.
+fn tags_match(candidate: &FileMetadata, missing: &FileMetadata) -> bool {
+    let Some(title) = missing.title.as_deref().map(normalize) else {
+        return false;
+    };
+    let candidate_title = candidate
+        .title
+        .as_deref()
+        .map(normalize)
+        .unwrap_or_default();
+    let candidate_full = normalize(&format!(
+        "{} - {}",
+        candidate.display_artist(),
+        candidate.display_title()
+    ));
+    let artist_agrees = match (&missing.artist, &candidate.artist) {
+        (Some(missing), Some(candidate)) => normalize(missing) == normalize(candidate),
+        _ => true,
+    };
+    !title.is_empty() && (title == candidate_title || title == candidate_full) && artist_agrees
+}
+
+/// Searches the library for a missing entry: by file name first, then by tags, then by length alone.
+/// `tagged` holds the library's tags once they were needed.
+fn find_candidates(
+    missing: &FileMetadata,
+    library: &[PathBuf],
+    tagged: &mut Option<Vec<FileMetadata>>,
+) -> Option<(Vec<FileMetadata>, MatchKind)> {
+    let stem = normalized_stem(&missing.file_path);
+    if !stem.is_empty() {
+        let by_name: Vec<FileMetadata> = library
+            .iter()
+            .filter(|path| normalized_stem(path) == stem)
+            .map(|path| FileMetadata::get_file_data(path))
+            .collect();
+        if !by_name.is_empty() {
+            return Some((by_name, MatchKind::FileName));
+        }
+    }
+
+    if missing.title.is_none() && missing.duration_as_secs.is_none() {
+        return None;
+    }
+    let tagged = tagged.get_or_insert_with(|| {
+        library
+            .iter()
+            .map(|path| FileMetadata::get_file_data(path))
+            .collect()
+    });
+    let by_tags: Vec<FileMetadata> = tagged
+        .iter()
+        .filter(|candidate| tags_match(candidate, missing))
+        .cloned()
+        .collect();
+    if !by_tags.is_empty() {
+        return Some((by_tags, MatchKind::Tags));
+    }
+
+    let by_length: Vec<FileMetadata> = tagged
+        .iter()
+        .filter(|candidate| {
+            length_off(candidate, missing).is_some_and(|off| off <= DURATION_ONLY_TOLERANCE_SECS)
+        })
+        .cloned()
+        .collect();
+    (!by_length.is_empty()).then_some((by_length, MatchKind::Duration))
+}
+
+/// Walks the music directory and looks for replacements for every missing entry of `items`.
+fn search_repairs(music_directory: &Path, items: &[FileMetadata]) -> RepairSearch {
+    let library = playable_files(music_directory);
+    let mut tagged = None;
+
+    let repairs = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !item.file_path.exists())
+        .map(|(index, item)| {
+            let (candidates, kind) = find_candidates(item, &library, &mut tagged)
+                .unwrap_or((Vec::new(), MatchKind::FileName));
+            let (candidates, accepted) = rank(candidates, item);
+            Repair {
+                accepted: accepted && !matches!(kind, MatchKind::Duration),
+                candidates,
+                chosen: 0,
+                index,
+                kind,
+            }
+        })
+        .collect();
+    (library, repairs)
+}
+
+impl App {
+    /// Opens the review dialog for a playlist's missing entries and looks for replacements in the background.
+    /// The music directory is walked again, so files moved since the last walk are found.
+    pub fn start_repair(&mut self, playlist: PathBuf, items: Vec<FileMetadata>, mode: EnqueueMode) {
+        let (sender, receiver) = mpsc::channel();
+        let music_directory = self.config.directories.music_directory.clone();
+        let search_items = items.clone();
+        thread::spawn(move || {
+            let _ = sender.send(search_repairs(&music_directory, &search_items));
+        });
+        self.repair_search = Some(receiver);
+
+        self.popup = Some(Popup::Repair {
+            items,
+            mode,
+            playlist,
+            repairs: Vec::new(),
+            searching: true,
+            selected: 0,
+        });
+    }
+
+    /// Fills in the review dialog once the search is done; the result is dropped if the dialog was closed.
+    pub fn poll_repair_search(&mut self) {
+        let Some(receiver) = &self.repair_search else {
+            return;
+        };
+        let (library, found) = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.repair_search = None;
+                return;
+            }
+        };
+        self.repair_search = None;
+        self.library = Some(library);
+        if let Some(Popup::Repair {
+            repairs, searching, ..
+        }) = &mut self.popup
+            && *searching
+        {
+            *repairs = found;
+            *searching = false;
+        }
+    }
+
+    /// Swaps in the accepted replacements, saves the repaired playlist and loads it.
+    pub fn apply_repairs(
+        &mut self,
+        playlist: &Path,
+        mut items: Vec<FileMetadata>,
+        repairs: Vec<Repair>,
+        mode: EnqueueMode,
+    ) {
+        let mut repaired = false;
+        for repair in repairs.iter().filter(|repair| repair.accepted) {
+            if let Some(replacement) = repair.replacement() {
+                items[repair.index] = replacement.clone();
+                repaired = true;
+            }
+        }
+        if repaired && let Err(e) = write_playlist_file(playlist, &items) {
+            self.show_message(format!("Failed to save playlist: {}", e));
+        }
+        if let Tab::Saved = self.tab {
+            self.refresh_saved_playlists();
+        }
+        self.queue_playlist_items(items, mode);
+    }
+}
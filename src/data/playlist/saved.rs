@@ -0,0 +1,614 @@
+use crate::{
+    data::{
+        metadata::file_metadata::FileMetadata,
+        playlist::{
+            playlist_funcs::{is_playlist, read_playlist, write_playlist_file},
+            smart::{
+                SMART_EXTENSION, SmartRequest, SmartSource, is_smart_playlist, read_smart_query,
+            },
+        },
+        queue::play_queue::{EnqueueMode, PlayQueue},
+    },
+    tui::{
+        app::{App, Tab},
+        popup::{InputAction, PendingAction, Popup},
+    },
+};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::{ListItem, ListState},
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// A playlist in the playlist directory, with the tracks it held when last read.
+/// Smart playlists have a query instead, and hold the tracks it found when last opened.
+pub struct SavedPlaylist {
+    pub from_config: bool,
+    pub items: Vec<FileMetadata>,
+    /// False until the playlist's tracks were read in the background.
+    pub loaded: bool,
+    /// Indices of the items whose file is gone, found when the playlist was read.
+    pub missing: HashSet<usize>,
+    pub path: PathBuf,
+    pub query: Option<String>,
+}
+
+/// A playlist file's tracks as read in the background, with the indices of those whose file is gone.
+type LoadedPlaylist = (PathBuf, Vec<FileMetadata>, HashSet<usize>);
+
+impl SavedPlaylist {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Describes the playlist's length, e.g. "12 tracks, 48:10"; a '+' marks tracks of unknown length.
+    /// Smart playlists say so, and only know their length once opened.
+    pub fn summary(&self) -> String {
+        if !self.loaded {
+            return "...".to_string();
+        }
+        let (total, unknown) = PlayQueue::sum_durations(self.items.iter());
+        let smart = match self.query.is_some() {
+            true if self.items.is_empty() => return "smart".to_string(),
+            true => "smart, ",
+            false => "",
+        };
+        format!(
+            "{}{} track{}, {}{}",
+            smart,
+            self.items.len(),
+            if self.items.len() == 1 { "" } else { "s" },
+            FileMetadata::format_secs(total),
+            if 0 < unknown { "+" } else { "" }
+        )
+    }
+}
+
+/// Encapsulates the playlists saved in the playlist directory and the ones marked for merging.
+pub struct SavedPlaylists {
+    pub list_state: ListState,
+    loading: Option<Receiver<LoadedPlaylist>>,
+    pub marked: HashSet<PathBuf>,
+    pub playlists: Vec<SavedPlaylist>,
+    pub selected: usize,
+}
+
+impl SavedPlaylists {
+    pub fn new() -> Self {
+        Self {
+            list_state: ListState::default(),
+            loading: None,
+            marked: HashSet::new(),
+            playlists: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    /// Lists the playlists in `dir` again, sorted by name, followed by the smart playlists defined in the config.
+    /// Their tracks are read in the background; until then a playlist keeps what it held before.
+    /// The cursor stays on the same playlist if it's still there.
+    pub fn refresh(&mut self, dir: &Path, smart_playlists: &BTreeMap<String, String>) {
+        let selected = self
+            .selected_playlist()
+            .map(|playlist| playlist.path.clone());
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.is_file() && (is_playlist(path) || is_smart_playlist(path)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort_by_key(|path| path.to_string_lossy().to_lowercase());
+
+        let mut previous: Vec<SavedPlaylist> = self.playlists.drain(..).collect();
+        let mut take_previous = |path: &Path| {
+            previous
+                .iter()
+                .position(|playlist| playlist.path == path)
+                .map(|index| previous.swap_remove(index))
+        };
+        let to_load: Vec<PathBuf> = paths
+            .iter()
+            .filter(|path| !is_smart_playlist(path))
+            .cloned()
+            .collect();
+        self.playlists = paths
+            .into_iter()
+            .map(|path| {
+                let query =
+                    is_smart_playlist(&path).then(|| read_smart_query(&path).unwrap_or_default());
+                let (items, missing) = take_previous(&path)
+                    .map(|playlist| (playlist.items, playlist.missing))
+                    .unwrap_or_default();
+                SavedPlaylist {
+                    from_config: false,
+                    items,
+                    loaded: query.is_some(),
+                    missing,
+                    path,
+                    query,
+                }
+            })
+            .collect();
+        // A file of the same name takes precedence over a smart playlist in the config.
+        for (name, query) in smart_playlists {
+            let path = dir.join(format!("{}.{}", name, SMART_EXTENSION));
+            if !self.playlists.iter().any(|playlist| playlist.path == path) {
+                let items = take_previous(&path)
+                    .map(|playlist| playlist.items)
+                    .unwrap_or_default();
+                self.playlists.push(SavedPlaylist {
+                    from_config: true,
+                    items,
+                    loaded: true,
+                    missing: HashSet::new(),
+                    path,
+                    query: Some(query.clone()),
+                });
+            }
+        }
+        self.marked
+            .retain(|path| self.playlists.iter().any(|playlist| &playlist.path == path));
+        if let Some(index) =
+            selected.and_then(|selected| self.playlists.iter().position(|p| p.path == selected))
+        {
+            self.selected = index;
+        }
+        self.sync_selection();
+
+        // A load still running for an earlier refresh stops once its results can't be sent.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for path in to_load {
+                let items = read_playlist(&path).unwrap_or_default();
+                let missing = items
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| !item.file_path.exists())
+                    .map(|(index, _)| index)
+                    .collect();
+                if sender.send((path, items, missing)).is_err() {
+                    break;
+                }
+            }
+        });
+        self.loading = Some(receiver);
+    }
+
+    /// Takes in the playlists the background load has read so far, without waiting.
+    pub fn poll_loaded(&mut self) {
+        let Some(receiver) = &self.loading else {
+            return;
+        };
+        for (path, items, missing) in receiver.try_iter() {
+            if let Some(playlist) = self
+                .playlists
+                .iter_mut()
+                .find(|playlist| playlist.path == path)
+            {
+                playlist.items = items;
+                playlist.missing = missing;
+                playlist.loaded = true;
+            }
+        }
+    }
+
+    pub fn selected_playlist(&self) -> Option<&SavedPlaylist> {
+        self.playlists.get(self.selected)
+    }
+
+    /// Clamps the cursor to the list and updates the list state.
+    pub fn sync_selection(&mut self) {
+        self.selected = self.selected.min(self.playlists.len().saturating_sub(1));
+        self.list_state
+            .select((!self.playlists.is_empty()).then_some(self.selected));
+    }
+
+    /// Moves the cursor up one element or goes to the bottom if at the top.
+    pub fn navigate_up(&mut self) {
+        if self.playlists.is_empty() {
+            return;
+        }
+        if let 0 = self.selected {
+            self.selected = self.playlists.len() - 1
+        } else {
+            self.selected -= 1
+        }
+        self.sync_selection();
+    }
+
+    /// Moves the cursor down one element or goes to the top if at the bottom.
+    pub fn navigate_down(&mut self) {
+        if self.playlists.is_empty() {
+            return;
+        }
+        if self.selected < self.playlists.len() - 1 {
+            self.selected += 1
+        } else {
+            self.selected = 0
+        }
+        self.sync_selection();
+    }
+
+    /// Moves the cursor to the top of the list.
+    pub fn goto_top(&mut self) {
+        self.selected = 0;
+        self.sync_selection();
+    }
+
+    /// Moves the cursor to the bottom of the list.
+    pub fn goto_bottom(&mut self) {
+        self.selected = self.playlists.len().saturating_sub(1);
+        self.sync_selection();
+    }
+
+    /// Marks the selected playlist for merging, or unmarks it.
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self
+            .selected_playlist()
+            .map(|playlist| playlist.path.clone())
+        else {
+            return;
+        };
+        if !self.marked.remove(&path) {
+            self.marked.insert(path);
+        }
+    }
+
+    /// Returns the playlists to merge: the marked ones in list order, or the selected one if none are marked.
+    pub fn merge_sources(&self) -> Vec<PathBuf> {
+        match self.marked.is_empty() {
+            true => self
+                .selected_playlist()
+                .map(|playlist| vec![playlist.path.clone()])
+                .unwrap_or_default(),
+            false => self
+                .playlists
+                .iter()
+                .filter(|playlist| self.marked.contains(&playlist.path))
+                .map(|playlist| playlist.path.clone())
+                .collect(),
+        }
+    }
+
+    /// Lists the playlists with their length; marked ones are prefixed with '*'.
+    pub fn list_items(&self) -> Vec<ListItem<'_>> {
+        self.playlists
+            .iter()
+            .map(|playlist| {
+                let marked = self.marked.contains(&playlist.path);
+                let style = Style::default().fg(Color::White);
+                ListItem::new(format!(
+                    "{}{}  ({})",
+                    if marked { "* " } else { "" },
+                    playlist.name(),
+                    playlist.summary()
+                ))
+                .style(match marked {
+                    true => style.add_modifier(Modifier::BOLD),
+                    false => style,
+                })
+            })
+            .collect()
+    }
+
+    /// Lists the selected playlist's tracks; tracks whose file is gone are dimmed.
+    /// A smart playlist that wasn't opened yet shows its query.
+    pub fn preview_items(&self) -> Vec<ListItem<'_>> {
+        let Some(playlist) = self.selected_playlist() else {
+            return Vec::new();
+        };
+        if !playlist.loaded {
+            return vec![ListItem::new("(reading...)").style(Style::default().fg(Color::DarkGray))];
+        }
+        if let (Some(query), true) = (&playlist.query, playlist.items.is_empty()) {
+            return vec![
+                ListItem::new(query.as_str()).style(Style::default().fg(Color::White)),
+                ListItem::new("(open to find its tracks)")
+                    .style(Style::default().fg(Color::DarkGray)),
+            ];
+        }
+        playlist
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                ListItem::new(format!(
+                    "{} - {}  {}",
+                    item.display_artist(),
+                    item.display_title(),
+                    item.display_duration_display()
+                ))
+                .style(match playlist.missing.contains(&index) {
+                    true => Style::default().fg(Color::DarkGray),
+                    false => Style::default().fg(Color::White),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns `name` as a playlist file name, keeping its extension if it has a playlist one
+/// and taking `fallback_ext` otherwise.
+fn playlist_file_name(name: &str, fallback_ext: &str) -> String {
+    match is_playlist(Path::new(name)) || is_smart_playlist(Path::new(name)) {
+        true => name.to_string(),
+        false => format!("{}.{}", name, fallback_ext),
+    }
+}
+
+/// Copies a playlist to `target`. If the extensions name different formats, the tracks are
+/// read and written in the target's format; a smart playlist only copies to another smart playlist.
+fn copy_playlist(path: &Path, target: &Path) -> io::Result<()> {
+    let same_format = extension_of(path).eq_ignore_ascii_case(&extension_of(target));
+    match same_format || is_smart_playlist(path) {
+        true => fs::copy(path, target).map(|_| ()),
+        false => write_playlist_file(target, &read_playlist(path)?),
+    }
+}
+
+/// Returns the file `name` gives a copy or renamed version of the playlist at `path`.
+/// Smart playlists keep their extension, as their query can't be written in another format.
+pub fn target_path(path: &Path, name: &str) -> PathBuf {
+    let file_name = playlist_file_name(name, &extension_of(path));
+    let target = path.with_file_name(&file_name);
+    match is_smart_playlist(path) && !is_smart_playlist(&target) {
+        true => target.with_extension(SMART_EXTENSION),
+        false => target,
+    }
+}
+
+/// Returns the extension of a playlist path, "m3u8" if it has none.
+pub fn extension_of(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or("m3u8".to_string())
+}
+
+impl App {
+    /// Switches to the saved playlists tab, reading the playlist directory again.
+    pub fn show_saved_playlists(&mut self) {
+        self.tab = Tab::Saved;
+        self.refresh_saved_playlists();
+    }
+
+    pub fn refresh_saved_playlists(&mut self) {
+        self.saved_playlists.refresh(
+            &self.config.directories.playlist_directory,
+            &self.config.smart_playlists,
+        );
+    }
+
+    /// Loads the selected saved playlist: `Play` replaces the queue with it, the other modes enqueue it.
+    /// Smart playlists are run against the music directory first.
+    pub fn handle_open_saved(&mut self, mode: EnqueueMode) {
+        let Some(playlist) = self.saved_playlists.selected_playlist() else {
+            return;
+        };
+        let (path, query) = (playlist.path.clone(), playlist.query.clone());
+        match query {
+            Some(query) => {
+                if let Some(query) = self.smart_query(&query) {
+                    let sources = vec![SmartSource::Query(query)];
+                    self.start_smart_run(SmartRequest::Open { mode, path }, sources);
+                }
+            }
+            None => self.load_playlist(&path, mode),
+        }
+    }
+
+    /// Shows and queues the tracks a smart playlist found.
+    pub fn open_smart_tracks(&mut self, path: &Path, tracks: Vec<FileMetadata>, mode: EnqueueMode) {
+        if let Some(playlist) = self
+            .saved_playlists
+            .playlists
+            .iter_mut()
+            .find(|playlist| playlist.path == path)
+        {
+            playlist.items = tracks.clone();
+            playlist.missing.clear();
+        }
+        if !tracks.is_empty() {
+            self.queue_playlist_items(tracks, mode);
+        }
+    }
+
+    /// Asks for a new name for the selected playlist; smart playlists from the config are renamed there.
+    pub fn handle_rename_saved(&mut self) {
+        let Some(playlist) = self
+            .saved_playlists
+            .selected_playlist()
+            .filter(|playlist| !playlist.from_config)
+        else {
+            return;
+        };
+        self.popup = Some(Popup::Input {
+            prompt: format!("Rename {} to:", playlist.name()),
+            input: playlist.name(),
+            action: InputAction::RenamePlaylist(playlist.path.clone()),
+        });
+    }
+
+    /// Asks for the name of a copy of the selected playlist.
+    pub fn handle_duplicate_saved(&mut self) {
+        let Some(playlist) = self.saved_playlists.selected_playlist() else {
+            return;
+        };
+        let stem = playlist
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.popup = Some(Popup::Input {
+            prompt: format!("Copy {} as:", playlist.name()),
+            input: format!("{} copy", stem),
+            action: InputAction::DuplicatePlaylist(playlist.path.clone()),
+        });
+    }
+
+    /// Asks for the name of a playlist joining the marked playlists, in list order.
+    pub fn handle_merge_saved(&mut self) {
+        let sources = self.saved_playlists.merge_sources();
+        if sources.len() < 2 {
+            return;
+        }
+        self.popup = Some(Popup::Input {
+            prompt: format!("Merge {} playlists into:", sources.len()),
+            input: String::new(),
+            action: InputAction::MergePlaylists(sources),
+        });
+    }
+
+    /// Asks before deleting the selected playlist; smart playlists from the config are deleted there.
+    pub fn handle_delete_saved(&mut self) {
+        let Some(playlist) = self
+            .saved_playlists
+            .selected_playlist()
+            .filter(|playlist| !playlist.from_config)
+        else {
+            return;
+        };
+        self.popup = Some(Popup::Confirm {
+            message: format!("Delete {}?", playlist.name()),
+            action: PendingAction::DeletePlaylist(playlist.path.clone()),
+        });
+    }
+
+    /// Returns true if a playlist called `name` (with the extension it would get) already exists.
+    pub fn saved_playlist_exists(&self, name: &str, fallback_ext: &str) -> bool {
+        self.config
+            .directories
+            .playlist_directory
+            .join(playlist_file_name(name, fallback_ext))
+            .exists()
+    }
+
+    /// Renames a playlist, keeping its extension unless the new name has a playlist one.
+    /// A new extension converts the playlist to that format.
+    pub fn rename_saved(&mut self, path: &Path, name: &str) {
+        let target = target_path(path, name);
+        let renamed = match extension_of(path).eq_ignore_ascii_case(&extension_of(&target)) {
+            true => fs::rename(path, &target),
+            false => copy_playlist(path, &target).and_then(|_| fs::remove_file(path)),
+        };
+        if let Err(e) = renamed {
+            self.show_message(format!("Failed to rename playlist: {}", e));
+        }
+        self.refresh_saved_playlists();
+    }
+
+    /// Copies a playlist under a new name in the same directory, converting it if the new name has another playlist extension.
+    /// Copying a smart playlist from the config writes its query to a file.
+    pub fn duplicate_saved(&mut self, path: &Path, name: &str) {
+        let target = target_path(path, name);
+        let config_query = self
+            .saved_playlists
+            .playlists
+            .iter()
+            .find(|playlist| playlist.path == path && playlist.from_config)
+            .and_then(|playlist| playlist.query.clone());
+        let copied = match config_query {
+            Some(query) => fs::write(&target, query + "\n"),
+            None => copy_playlist(path, &target),
+        };
+        if let Err(e) = copied {
+            self.show_message(format!("Failed to copy playlist: {}", e));
+        }
+        self.refresh_saved_playlists();
+    }
+
+    /// Writes the tracks of several playlists, one after another, to a new playlist.
+    /// Smart playlists contribute the tracks they find now, so the tracks are gathered in the background.
+    pub fn merge_saved(&mut self, sources: &[PathBuf], name: &str) {
+        let sources = sources
+            .iter()
+            .filter_map(|path| {
+                let query = self
+                    .saved_playlists
+                    .playlists
+                    .iter()
+                    .find(|playlist| &playlist.path == path)
+                    .and_then(|playlist| playlist.query.clone());
+                match query {
+                    Some(query) => self.smart_query(&query).map(SmartSource::Query),
+                    None => Some(SmartSource::File(path.clone())),
+                }
+            })
+            .collect();
+        self.saved_playlists.marked.clear();
+        self.start_smart_run(
+            SmartRequest::Merge {
+                name: name.to_string(),
+            },
+            sources,
+        );
+    }
+
+    /// Writes merged tracks to a new playlist called `name`.
+    pub fn write_merged(&mut self, name: &str, items: &[FileMetadata]) {
+        let target = self
+            .config
+            .directories
+            .playlist_directory
+            .join(playlist_file_name(name, "m3u8"));
+        if let Err(e) = write_playlist_file(&target, items) {
+            self.show_message(format!("Failed to save playlist: {}", e));
+        }
+        self.refresh_saved_playlists();
+    }
+
+    pub fn delete_saved(&mut self, path: &Path) {
+        if let Err(e) = fs::remove_file(path) {
+            self.show_message(format!("Failed to delete playlist: {}", e));
+        }
+        self.refresh_saved_playlists();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_new_extension_converts_the_playlist() {
+        let dir = std::env::temp_dir().join(format!("rmpr-saved-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("list.m3u8");
+        fs::write(&source, "#EXTM3U\n#EXTINF:215,Artist - Title\nsong.mp3\n").unwrap();
+
+        let target = target_path(&source, "list.xspf");
+        copy_playlist(&source, &target).unwrap();
+        let written = fs::read_to_string(&target).unwrap();
+        let items = read_playlist(&target).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(written.starts_with("<?xml"));
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].file_path, dir.join("song.mp3"));
+        assert_eq!(items[0].title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn smart_playlists_keep_their_extension() {
+        let smart = Path::new("/lists/rock.smart");
+        assert_eq!(target_path(smart, "metal"), Path::new("/lists/metal.smart"));
+        assert_eq!(
+            target_path(smart, "metal.m3u"),
+            Path::new("/lists/metal.smart")
+        );
+        assert_eq!(
+            target_path(Path::new("/lists/rock.m3u"), "metal"),
+            Path::new("/lists/metal.m3u")
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    data::{
+        library::{
+            query::{Query, parse_query},
+            scan::playable_files,
+        },
+        metadata::{cue::tracks_in_files, file_metadata::FileMetadata},
+        playlist::playlist_funcs::read_playlist,
+        queue::play_queue::EnqueueMode,
+    },
+    tui::app::App,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+/// Smart playlist files hold a query instead of tracks.
+pub const SMART_EXTENSION: &str = "smart";
+
+/// Shown while a smart playlist run walks the music directory.
+const SEARCHING: &str = "Searching the music directory...";
+
+/// What the tracks of a smart playlist run are for.
+pub enum SmartRequest {
+    /// Opening the saved playlist at `path`.
+    Open { mode: EnqueueMode, path: PathBuf },
+    /// Merging saved playlists into a new one called `name`.
+    Merge { name: String },
+}
+
+/// A playlist whose tracks a smart playlist run gathers: a query to run, or a playlist file to read.
+pub enum SmartSource {
+    File(PathBuf),
+    Query(Query),
+}
+
+/// A finished smart playlist run: its request, the music directory if it was walked, and the tracks found.
+pub type SmartRun = (SmartRequest, Option<Vec<PathBuf>>, Vec<FileMetadata>);
+
+/// Returns true if the file is a smart playlist.
+pub fn is_smart_playlist(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(SMART_EXTENSION))
+}
+
+/// Reads the query of a smart playlist file; its lines are joined and lines starting with '#' are skipped.
+pub fn read_smart_query(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+impl App {
+    /// Parses a smart playlist query, or shows why it doesn't parse.
+    pub fn smart_query(&mut self, query: &str) -> Option<Query> {
+        match parse_query(query) {
+            Ok(query) => Some(query),
+            Err(e) => {
+                self.show_message(format!("Invalid smart playlist query: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Gathers the tracks of `sources` on a background thread; `poll_smart_run` hands them to the request.
+    /// Queries walk the music directory again, so files added since the last walk are found.
+    pub fn start_smart_run(&mut self, request: SmartRequest, sources: Vec<SmartSource>) {
+        let (sender, receiver) = mpsc::channel();
+        let music_directory = self.config.directories.music_directory.clone();
+        thread::spawn(move || {
+            let mut library = None;
+            let tracks = sources
+                .into_iter()
+                .flat_map(|source| match source {
+                    SmartSource::File(path) => read_playlist(&path).unwrap_or_default(),
+                    SmartSource::Query(query) => {
+                        let library =
+                            library.get_or_insert_with(|| playable_files(&music_directory));
+                        query.run(tracks_in_files(library))
+                    }
+                })
+                .collect();
+            let _ = sender.send((request, library, tracks));
+        });
+        self.smart_run = Some(receiver);
+        self.show_message(SEARCHING.to_string());
+    }
+
+    /// Opens or merges the tracks of a finished smart playlist run.
+    pub fn poll_smart_run(&mut self) {
+        let Some(receiver) = &self.smart_run else {
+            return;
+        };
+        let (request, library, tracks) = match receiver.try_recv() {
+            Ok(run) => run,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.smart_run = None;
+                return;
+            }
+        };
+        self.smart_run = None;
+        if self
+            .message
+            .as_ref()
+            .is_some_and(|(message, _)| message == SEARCHING)
+        {
+            self.message = None;
+        }
+        if library.is_some() {
+            self.library = library;
+        }
+        match request {
+            SmartRequest::Open { mode, path } => self.open_smart_tracks(&path, tracks, mode),
+            SmartRequest::Merge { name } => self.write_merged(&name, &tracks),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::data::{
+    metadata::file_metadata::FileMetadata,
+    playlist::{
+        PlaylistEntry, percent_decode, percent_encode, read_vlc_option, relative_entry_path,
+        resolve_entry_path, vlc_options,
+    },
+};
+use roxmltree::{Document, Node};
+use std::path::Path;
+
+/// Parses an XSPF playlist; relative locations resolve against `base`.
+/// Malformed XML yields no entries. VLC's `start-time`/`stop-time` options limit a track to part of its file.
+pub fn parse_xspf(content: &str, base: &Path) -> Vec<PlaylistEntry> {
+    let Ok(document) = Document::parse(content.trim_start_matches('\u{feff}')) else {
+        return Vec::new();
+    };
+    let child_text = |track: Node, name: &str| {
+        track
+            .children()
+            .find(|child| child.tag_name().name() == name)
+            .and_then(|child| child.text())
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    document
+        .descendants()
+        .filter(|node| node.tag_name().name() == "track")
+        .filter_map(|track| {
+            let location = child_text(track, "location")?;
+            let path = match location.starts_with("file://") {
+                true => resolve_entry_path(&location, base),
+                false => resolve_entry_path(&percent_decode(&location), base),
+            }?;
+            let mut cue = None;
+            track
+                .descendants()
+                .filter(|node| node.tag_name().name() == "option")
+                .filter_map(|node| node.text())
+                .for_each(|option| read_vlc_option(option, &mut cue));
+            Some(PlaylistEntry {
+                album: child_text(track, "album"),
+                artist: child_text(track, "creator"),
+                cue,
+                duration: child_text(track, "duration")
+                    .and_then(|millis| millis.parse::<f64>().ok())
+                    .map(|millis| millis / 1000.0),
+                path,
+                title: child_text(track, "title"),
+            })
+        })
+        .collect()
+}
+
+/// Escapes text for use in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Writes an XSPF playlist that will be saved at `playlist`.
+pub fn write_xspf(items: &[FileMetadata], playlist: &Path) -> String {
+    let mut content = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\" \
+         xmlns:vlc=\"http://www.videolan.org/vlc/playlist/ns/0/\">\n  <trackList>\n",
+    );
+    for item in items {
+        let location = relative_entry_path(&item.file_path, playlist);
+        let location = match Path::new(&location).is_absolute() {
+            true => format!("file://{}", percent_encode(&location)),
+            false => percent_encode(&location),
+        };
+        content.push_str("    <track>\n");
+        content.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape_xml(&location)
+        ));
+        let fields = [
+            ("title", item.title.clone()),
+            ("creator", item.artist.clone()),
+            ("album", item.album.clone()),
+            (
+                "duration",
+                item.duration_as_secs
+                    .map(|duration| format!("{}", (duration * 1000.0).round() as u64)),
+            ),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                content.push_str(&format!(
+                    "      <{}>{}</{}>\n",
+                    name,
+                    escape_xml(&value),
+                    name
+                ));
+            }
+        }
+        if let Some(cue) = &item.cue {
+            content.push_str(
+                "      <extension application=\"http://www.videolan.org/vlc/playlist/0\">\n",
+            );
+            for option in vlc_options(cue) {
+                content.push_str(&format!("        <vlc:option>{}</vlc:option>\n", option));
+            }
+            content.push_str("      </extension>\n");
+        }
+        content.push_str("    </track>\n");
+    }
+    content.push_str("  </trackList>\n</playlist>\n");
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::playlist::tests::fixtures;
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn parses_tracks() {
+        let dir = fixtures();
+        let entries = parse_xspf(&fs::read_to_string(dir.join("mixed.xspf")).unwrap(), &dir);
+
+        // The http:// stream is skipped.
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                dir.join("tagged.mp3"),
+                dir.join("Sub Dir/untagged.flac"),
+                PathBuf::from("/tmp/My Music/song#1.ogg"),
+            ]
+        );
+        assert_eq!(entries[1].title.as_deref(), Some("Playlist Title"));
+        assert_eq!(entries[1].artist.as_deref(), Some("Playlist Artist"));
+        assert_eq!(entries[1].album.as_deref(), Some("Playlist Album"));
+        assert_eq!(entries[1].duration, Some(215.0));
+        assert_eq!(entries[2].title.as_deref(), Some("Rock & Roll"));
+    }
+
+    #[test]
+    fn malformed_xml_has_no_tracks() {
+        assert!(parse_xspf("<playlist><trackList><track>", &fixtures()).is_empty());
+    }
+
+    #[test]
+    fn writes_locations_as_uris() {
+        let dir = fixtures();
+        let items: Vec<_> = parse_xspf(&fs::read_to_string(dir.join("mixed.xspf")).unwrap(), &dir)
+            .into_iter()
+            .map(PlaylistEntry::into_metadata)
+            .collect();
+        let written = write_xspf(&items, &dir.join("written.xspf"));
+
+        assert!(written.contains("<location>Sub%20Dir/untagged.flac</location>"));
+        assert!(written.contains("<location>file:///tmp/My%20Music/song%231.ogg</location>"));
+        assert!(written.contains("<title>Rock &amp; Roll</title>"));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::data::metadata::file_metadata::FileMetadata;
+use ratatui::{
+    style::{Color, Style},
+    widgets::{ListItem, ListState},
+};
+use std::collections::VecDeque;
+
+/// Encapsulates the recently played tracks, newest first.
+pub struct History {
+    pub items: VecDeque<FileMetadata>,
+    pub list_state: ListState,
+    pub max_len: usize,
+    pub selected: usize,
+}
+
+impl History {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            items: VecDeque::new(),
+            list_state: ListState::default(),
+            max_len,
+            selected: 0,
+        }
+    }
+
+    /// Records a track as played, dropping the oldest entry once full.
+    /// A track restarting (e.g. with repeat one) isn't recorded twice in a row.
+    pub fn push(&mut self, item: FileMetadata) {
+        if self
+            .items
+            .front()
+            .is_some_and(|last| last.id() == item.id())
+        {
+            return;
+        }
+        self.items.push_front(item);
+        self.items.truncate(self.max_len);
+    }
+
+    /// Removes and returns the most recently played track.
+    pub fn pop(&mut self) -> Option<FileMetadata> {
+        self.items.pop_front()
+    }
+
+    /// Clamps the cursor to the history and updates the list state.
+    pub fn sync_selection(&mut self) {
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        self.list_state
+            .select((!self.items.is_empty()).then_some(self.selected));
+    }
+
+    /// Moves the cursor up one element or goes to the bottom if at the top.
+    pub fn navigate_up(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        if let 0 = self.selected {
+            self.selected = self.items.len() - 1
+        } else {
+            self.selected -= 1
+        }
+        self.sync_selection();
+    }
+
+    /// Moves the cursor down one element or goes to the top if at the bottom.
+    pub fn navigate_down(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        if self.selected < self.items.len() - 1 {
+            self.selected += 1
+        } else {
+            self.selected = 0
+        }
+        self.sync_selection();
+    }
+
+    /// Moves the cursor to the top of the list.
+    pub fn goto_top(&mut self) {
+        self.selected = 0;
+        self.sync_selection();
+    }
+
+    /// Moves the cursor to the bottom of the list.
+    pub fn goto_bottom(&mut self) {
+        self.selected = self.items.len().saturating_sub(1);
+        self.sync_selection();
+    }
+
+    /// Lists the played tracks by title, newest first.
+    pub fn list_items(&self) -> Vec<ListItem<'_>> {
+        self.items
+            .iter()
+            .map(|entry| {
+                ListItem::new(entry.title.as_ref().unwrap_or(&entry.raw_file).as_str())
+                    .style(Style::default().fg(Color::White))
+            })
+            .collect()
+    }
+}
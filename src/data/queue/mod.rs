@@ -1,3 +1,7 @@
-pub mod metadata_queue;
-pub mod music_queue;
+pub mod history;
+pub mod named_queue;
+pub mod named_queue_funcs;
+pub mod play_queue;
+pub mod playback_modes;
 pub mod queue_funcs;
+pub mod undo;
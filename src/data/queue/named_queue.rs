@@ -0,0 +1,128 @@
+use crate::data::{
+    metadata::{cue::cue_tracks, file_metadata::FileMetadata},
+    queue::{play_queue::PlayQueue, playback_modes::PlaybackModes, undo::UndoHistory},
+    session::{data_file, write_data_file},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::PathBuf};
+
+/// A queue with a name, keeping its items, current item and position while another queue plays.
+pub struct NamedQueue {
+    pub name: String,
+    pub position_millis: u64,
+    pub queue: PlayQueue,
+    pub undo: UndoHistory,
+}
+
+impl NamedQueue {
+    pub fn new(name: String, undo_depth: usize) -> Self {
+        Self {
+            name,
+            position_millis: 0,
+            queue: PlayQueue::new(),
+            undo: UndoHistory::new(undo_depth),
+        }
+    }
+}
+
+/// A queue item as written to queues.toml; CUE tracks also keep where they start.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SavedItem {
+    File(PathBuf),
+    Cue { path: PathBuf, start: f64 },
+}
+
+impl SavedItem {
+    fn of(item: &FileMetadata) -> Self {
+        match item.cue {
+            Some(cue) => SavedItem::Cue {
+                path: item.file_path.clone(),
+                start: cue.start,
+            },
+            None => SavedItem::File(item.file_path.clone()),
+        }
+    }
+
+    /// Reads the item's metadata again; a CUE track that is no longer in its sheet becomes the whole file.
+    fn load(self) -> FileMetadata {
+        match self {
+            SavedItem::File(path) => FileMetadata::get_file_data(&path),
+            SavedItem::Cue { path, start } => cue_tracks(&path)
+                .and_then(|tracks| {
+                    tracks.into_iter().find(|track| {
+                        track
+                            .cue
+                            .is_some_and(|cue| (cue.start - start).abs() < 0.001)
+                    })
+                })
+                .unwrap_or_else(|| FileMetadata::get_file_data(&path)),
+        }
+    }
+}
+
+/// A queue as written to queues.toml.
+#[derive(Serialize, Deserialize)]
+struct SavedQueue {
+    name: String,
+    current: Option<usize>,
+    #[serde(default = "PlaybackModes::new")]
+    modes: PlaybackModes,
+    position_millis: u64,
+    items: Vec<SavedItem>,
+}
+
+/// Every queue as written to queues.toml.
+#[derive(Serialize, Deserialize)]
+struct SavedQueues {
+    active: usize,
+    queues: Vec<SavedQueue>,
+}
+
+/// Loads the saved queues and the index of the active one.
+/// Falls back to a single empty queue if nothing was saved or the file can't be read.
+pub fn load_queues(undo_depth: usize) -> (Vec<NamedQueue>, usize) {
+    let saved = data_file("queues.toml")
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str::<SavedQueues>(&content).ok())
+        .filter(|saved| !saved.queues.is_empty());
+    let Some(saved) = saved else {
+        return (vec![NamedQueue::new("default".to_string(), undo_depth)], 0);
+    };
+
+    let active = saved.active.min(saved.queues.len() - 1);
+    let queues = saved
+        .queues
+        .into_iter()
+        .map(|saved| {
+            let mut named = NamedQueue::new(saved.name, undo_depth);
+            named.queue.modes = saved.modes;
+            named.queue.restore(
+                saved.items.into_iter().map(SavedItem::load).collect(),
+                saved.current,
+            );
+            named.position_millis = saved.position_millis;
+            named
+        })
+        .collect();
+    (queues, active)
+}
+
+/// Writes every queue to queues.toml so they come back next session.
+pub fn save_queues(queues: &[NamedQueue], active: usize) -> io::Result<()> {
+    let saved = SavedQueues {
+        active,
+        queues: queues
+            .iter()
+            .map(|named| SavedQueue {
+                name: named.name.clone(),
+                current: named.queue.current,
+                modes: named.queue.modes,
+                position_millis: named.position_millis,
+                items: named.queue.items.iter().map(SavedItem::of).collect(),
+            })
+            .collect(),
+    };
+    let content = toml::to_string(&saved).map_err(io::Error::other)?;
+    write_data_file("queues.toml", &content)
+}
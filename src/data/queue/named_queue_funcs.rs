@@ -0,0 +1,137 @@
+use crate::{
+    data::queue::{
+        named_queue::{NamedQueue, save_queues},
+        play_queue::PlayQueue,
+        undo::UndoHistory,
+    },
+    tui::{
+        app::App,
+        popup::{InputAction, PendingAction, Popup},
+    },
+};
+use std::{error::Error, mem, time::Duration};
+
+impl App {
+    /// Moves the active queue out of `App` into its slot in `queues`.
+    fn park_queue(&mut self) {
+        let slot = &mut self.queues[self.active_queue];
+        slot.queue = mem::replace(&mut self.queue, PlayQueue::new());
+        slot.undo = mem::replace(&mut self.undo, UndoHistory::new(0));
+        slot.position_millis = match self.audio.is_empty() {
+            true => 0,
+            false => self.audio.sink_pos_millis() as u64,
+        };
+    }
+
+    /// Moves the active queue from its slot in `queues` into `App`.
+    pub fn unpark_queue(&mut self) {
+        let slot = &mut self.queues[self.active_queue];
+        self.queue = mem::replace(&mut slot.queue, PlayQueue::new());
+        self.undo = mem::replace(&mut slot.undo, UndoHistory::new(0));
+    }
+
+    /// Starts the active queue's current item where it was left.
+    /// A `paused` queue is loaded on a paused sink, so nothing plays until it is resumed.
+    pub fn resume_queue(&mut self, paused: bool) {
+        let position = self.queues[self.active_queue].position_millis;
+        self.load_current(paused);
+        if self.queue.current.is_some() && 0 < position {
+            self.audio.seek(Duration::from_millis(position));
+        }
+    }
+
+    /// Hands playback over to the queue at `index`; the queue left behind keeps its position.
+    pub fn switch_queue(&mut self, index: usize) {
+        if index == self.active_queue || self.queues.len() <= index {
+            return;
+        }
+        let paused = self.audio.paused;
+        self.park_queue();
+        self.active_queue = index;
+        self.unpark_queue();
+        self.resume_queue(paused);
+    }
+
+    /// Switches to the next queue, wrapping around.
+    pub fn handle_next_queue(&mut self) {
+        self.switch_queue((self.active_queue + 1) % self.queues.len());
+    }
+
+    /// Switches to the previous queue, wrapping around.
+    pub fn handle_previous_queue(&mut self) {
+        self.switch_queue((self.active_queue + self.queues.len() - 1) % self.queues.len());
+    }
+
+    /// Asks for the name of a new queue.
+    pub fn handle_new_queue(&mut self) {
+        self.popup = Some(Popup::Input {
+            prompt: "Name of the new queue:".to_string(),
+            input: String::new(),
+            action: InputAction::NewQueue,
+        });
+    }
+
+    /// Asks for a new name for the active queue.
+    pub fn handle_rename_queue(&mut self) {
+        self.popup = Some(Popup::Input {
+            prompt: "Rename queue to:".to_string(),
+            input: self.queues[self.active_queue].name.clone(),
+            action: InputAction::RenameQueue,
+        });
+    }
+
+    /// Asks before deleting the active queue; the last queue can't be deleted.
+    pub fn handle_delete_queue(&mut self) {
+        if self.queues.len() <= 1 {
+            return;
+        }
+        self.popup = Some(Popup::Confirm {
+            message: format!(
+                "Delete queue '{}' ({} items)?",
+                self.queues[self.active_queue].name,
+                self.queue.len()
+            ),
+            action: PendingAction::DeleteQueue,
+        });
+    }
+
+    /// Returns true if a queue other than the active one already uses `name`.
+    pub fn queue_name_taken(&self, name: &str) -> bool {
+        self.queues
+            .iter()
+            .enumerate()
+            .any(|(index, named)| index != self.active_queue && named.name == name)
+    }
+
+    /// Adds an empty queue and switches to it.
+    pub fn create_queue(&mut self, name: String) {
+        self.queues
+            .push(NamedQueue::new(name, self.config.playback.undo_depth));
+        self.switch_queue(self.queues.len() - 1);
+    }
+
+    /// Renames the active queue.
+    pub fn rename_queue(&mut self, name: String) {
+        self.queues[self.active_queue].name = name;
+    }
+
+    /// Deletes the active queue and switches to its neighbour.
+    pub fn delete_queue(&mut self) {
+        if self.queues.len() <= 1 {
+            return;
+        }
+        let paused = self.audio.paused;
+        self.queues.remove(self.active_queue);
+        self.active_queue = self.active_queue.min(self.queues.len() - 1);
+        self.unpark_queue();
+        self.resume_queue(paused);
+    }
+
+    /// Saves every queue, including where the active one is.
+    pub fn save_queues(&mut self) -> Result<(), Box<dyn Error>> {
+        self.park_queue();
+        let saved = save_queues(&self.queues, self.active_queue);
+        self.unpark_queue();
+        Ok(saved?)
+    }
+}
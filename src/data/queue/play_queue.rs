@@ -0,0 +1,532 @@
+use crate::data::{
+    config::Colors,
+    metadata::file_metadata::{FileMetadata, TrackId},
+    queue::playback_modes::{PlaybackModes, Random, Repeat},
+};
+use rand::seq::{IndexedRandom, SliceRandom};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{ListItem, ListState},
+};
+use std::{collections::HashSet, str::FromStr};
+
+/// Longer column values are cut off so one long title doesn't push the other columns away.
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Where newly queued items go.
+#[derive(Clone, Copy)]
+pub enum EnqueueMode {
+    /// In front of the current item, playing the first new item right away.
+    Play,
+    /// Right after the current item.
+    Next,
+    /// At the end of the queue.
+    Append,
+}
+
+/// Encapsulates the playback queue; owns every queued file's metadata and points at the current one.
+/// Played items stay in the queue unless consume is on.
+#[derive(Clone)]
+pub struct PlayQueue {
+    pub current: Option<usize>,
+    pub items: Vec<FileMetadata>,
+    pub list_state: ListState,
+    pub modes: PlaybackModes,
+    pub played: HashSet<TrackId>,
+    pub selected: usize,
+    pub upcoming: Option<usize>,
+}
+
+impl PlayQueue {
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            items: Vec::new(),
+            list_state: ListState::default(),
+            modes: PlaybackModes::new(),
+            played: HashSet::new(),
+            selected: 0,
+            upcoming: None,
+        }
+    }
+
+    /// Returns how many items are queued.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns the current item's metadata.
+    pub fn current(&self) -> Option<&FileMetadata> {
+        self.current.and_then(|index| self.items.get(index))
+    }
+
+    /// Returns what identifies the current item.
+    pub fn current_id(&self) -> Option<TrackId> {
+        self.current().map(FileMetadata::id)
+    }
+
+    /// Returns the index that plays once the current item ends on its own.
+    pub fn next_index(&self) -> Option<usize> {
+        match self.modes.repeat {
+            Repeat::One => self.current,
+            _ => self.upcoming,
+        }
+    }
+
+    /// Returns the item that plays once the current item ends on its own.
+    pub fn next(&self) -> Option<&FileMetadata> {
+        self.next_index().and_then(|index| self.items.get(index))
+    }
+
+    /// Returns what identifies the item that plays once the current item ends on its own.
+    pub fn next_id(&self) -> Option<TrackId> {
+        self.next().map(FileMetadata::id)
+    }
+
+    /// Returns true if the item at `index` starts a run of items from one album.
+    fn starts_album(&self, index: usize) -> bool {
+        index == 0 || self.items[index].album != self.items[index - 1].album
+    }
+
+    /// Picks the item that follows the current one, ignoring repeat one.
+    /// Random picks skip items already played in this pass; with repeat all a new pass starts once everything was played.
+    fn pick_upcoming(&mut self) -> Option<usize> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let after = self.current.map(|current| current + 1).unwrap_or(0);
+        let repeat = self.modes.repeat != Repeat::Off;
+
+        if self.modes.random == Random::Off {
+            return match after < self.items.len() {
+                true => Some(after),
+                false => repeat.then_some(0),
+            };
+        }
+
+        if self.modes.random == Random::Album
+            && self.current.is_some()
+            && after < self.items.len()
+            && !self.starts_album(after)
+        {
+            return Some(after);
+        }
+
+        let candidates = |queue: &Self| -> Vec<usize> {
+            (0..queue.items.len())
+                .filter(|&index| Some(index) != queue.current)
+                .filter(|&index| queue.modes.random == Random::Track || queue.starts_album(index))
+                .filter(|&index| !queue.played.contains(&queue.items[index].id()))
+                .collect()
+        };
+        let mut options = candidates(self);
+        if options.is_empty() && repeat {
+            self.played.clear();
+            options = candidates(self);
+            if options.is_empty() {
+                return self.current.or(Some(0));
+            }
+        }
+        options.choose(&mut rand::rng()).copied()
+    }
+
+    /// Plans the item that follows the current one.
+    pub fn plan_upcoming(&mut self) {
+        self.upcoming = self.pick_upcoming();
+    }
+
+    /// Re-plans after an edit; a random pick is kept as long as it is still queued.
+    fn replan(&mut self) {
+        if self.modes.random == Random::Off || self.upcoming.is_none() {
+            self.plan_upcoming();
+        }
+    }
+
+    /// Appends an item to the end of the queue and returns its index.
+    pub fn push(&mut self, item: FileMetadata) -> usize {
+        self.items.push(item);
+        self.replan();
+        self.items.len() - 1
+    }
+
+    /// Inserts an item at `index` (clamped to the queue's length), keeping the current pointer on the same item.
+    pub fn insert(&mut self, index: usize, item: FileMetadata) -> usize {
+        let index = index.min(self.items.len());
+        self.items.insert(index, item);
+        let shift = |pointer: Option<usize>| match pointer {
+            Some(pointer) if index <= pointer => Some(pointer + 1),
+            pointer => pointer,
+        };
+        self.current = shift(self.current);
+        self.upcoming = shift(self.upcoming);
+        self.replan();
+        index
+    }
+
+    /// Removes the item at `index`.
+    /// Removing the current item makes the item that took its place current.
+    pub fn remove(&mut self, index: usize) -> Option<FileMetadata> {
+        if self.items.len() <= index {
+            return None;
+        }
+        let removed = self.items.remove(index);
+        self.current = match self.current {
+            Some(current) if index < current => Some(current - 1),
+            Some(current) if index == current && self.items.len() <= current => None,
+            current => current,
+        };
+        self.upcoming = match self.upcoming {
+            Some(upcoming) if index < upcoming => Some(upcoming - 1),
+            Some(upcoming) if index == upcoming => None,
+            upcoming => upcoming,
+        };
+        self.replan();
+        Some(removed)
+    }
+
+    /// Moves the item at `from` to `to`, keeping the current pointer on the same item.
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        if self.items.len() <= from || self.items.len() <= to || from == to {
+            return;
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+        let follow = |pointer: Option<usize>| {
+            pointer.map(|pointer| match pointer {
+                pointer if pointer == from => to,
+                pointer if from < pointer && pointer <= to => pointer - 1,
+                pointer if to <= pointer && pointer < from => pointer + 1,
+                pointer => pointer,
+            })
+        };
+        self.current = follow(self.current);
+        self.upcoming = follow(self.upcoming);
+        self.replan();
+    }
+
+    /// Shuffles the items after the current one (or every item if nothing is current).
+    pub fn shuffle_remaining(&mut self) {
+        let start = self.current.map(|current| current + 1).unwrap_or(0);
+        if start < self.items.len() {
+            self.items[start..].shuffle(&mut rand::rng());
+        }
+        self.plan_upcoming();
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.played.clear();
+        self.current = None;
+        self.upcoming = None;
+    }
+
+    /// Replaces the queue's items and current pointer, e.g. when undoing an edit.
+    pub fn restore(&mut self, items: Vec<FileMetadata>, current: Option<usize>) {
+        self.items = items;
+        self.current = current.filter(|&current| current < self.items.len());
+        self.plan_upcoming();
+    }
+
+    /// Makes the item at `index` current, if it exists.
+    pub fn set_current(&mut self, index: usize) {
+        if let Some(item) = self.items.get(index) {
+            self.played.insert(item.id());
+            self.current = Some(index);
+            self.plan_upcoming();
+        }
+    }
+
+    /// Moves on from the current item, removing it if consume is on.
+    /// `manual` skips ignore repeat one, like pressing next in any other player.
+    pub fn advance(&mut self, manual: bool) -> Option<&FileMetadata> {
+        let next = match manual {
+            true => self.upcoming,
+            false => self.next_index(),
+        };
+        let next = match (self.modes.consume, self.current) {
+            (true, Some(current)) if next != Some(current) => {
+                let next_id = next.map(|next| self.items[next].id());
+                self.remove(current);
+                next_id.and_then(|id| {
+                    // The removal shifted indices, so find the planned item again.
+                    match self.upcoming {
+                        Some(index) if self.items[index].id() == id => Some(index),
+                        _ => self.items.iter().position(|item| item.id() == id),
+                    }
+                })
+            }
+            _ => next,
+        };
+
+        match next {
+            Some(next) => self.set_current(next),
+            None => {
+                self.current = None;
+                self.upcoming = None;
+                self.played.clear();
+            }
+        }
+        self.current()
+    }
+
+    /// Sums the durations of the given items, also returning how many had no known duration.
+    pub fn sum_durations<'a>(items: impl Iterator<Item = &'a FileMetadata>) -> (f64, usize) {
+        items.fold((0.0, 0), |(total, unknown), item| {
+            match item.duration_as_secs {
+                Some(duration) => (total + duration, unknown),
+                None => (total, unknown + 1),
+            }
+        })
+    }
+
+    /// Returns the whole queue's duration in seconds and how many items have an unknown duration.
+    pub fn total_duration(&self) -> (f64, usize) {
+        Self::sum_durations(self.items.iter())
+    }
+
+    /// Returns the time left until the queue ends in seconds, given the current track's position,
+    /// and how many remaining items have an unknown duration.
+    pub fn remaining_duration(&self, position_secs: f64) -> (f64, usize) {
+        let Some(current) = self.current else {
+            return (0.0, 0);
+        };
+        let (rest, unknown) = Self::sum_durations(self.items.iter().skip(current + 1));
+        match self.items[current].duration_as_secs {
+            Some(duration) => (rest + (duration - position_secs).max(0.0), unknown),
+            None => (rest, unknown + 1),
+        }
+    }
+
+    /// Clamps the cursor to the queue and updates the list state.
+    pub fn sync_selection(&mut self) {
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        self.list_state
+            .select((!self.items.is_empty()).then_some(self.selected));
+    }
+
+    /// Moves the cursor up one element or goes to the bottom if at the top.
+    pub fn navigate_up(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        if let 0 = self.selected {
+            self.selected = self.items.len() - 1
+        } else {
+            self.selected -= 1
+        }
+        self.sync_selection();
+    }
+
+    /// Moves the cursor down one element or goes to the top if at the bottom.
+    pub fn navigate_down(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        if self.selected < self.items.len() - 1 {
+            self.selected += 1
+        } else {
+            self.selected = 0
+        }
+        self.sync_selection();
+    }
+
+    /// Moves the cursor to the top of the list.
+    pub fn goto_top(&mut self) {
+        self.selected = 0;
+        self.sync_selection();
+    }
+
+    /// Moves the cursor to the bottom of the list.
+    pub fn goto_bottom(&mut self) {
+        self.selected = self.items.len().saturating_sub(1);
+        self.sync_selection();
+    }
+
+    /// Lists the queue's items in the configured columns, with the current item in bold.
+    /// Each column is as wide as its widest value, up to `MAX_COLUMN_WIDTH`.
+    pub fn list_items(&self, columns: &[String], colors: &Colors) -> Vec<ListItem<'_>> {
+        let columns: Vec<&String> = columns
+            .iter()
+            .filter(|column| FileMetadata::new().display_field(column).is_some())
+            .collect();
+        let cells: Vec<Vec<String>> = self
+            .items
+            .iter()
+            .map(|entry| {
+                columns
+                    .iter()
+                    .filter_map(|column| entry.display_field(column))
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = (0..columns.len())
+            .map(|column| {
+                cells
+                    .iter()
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_COLUMN_WIDTH)
+            })
+            .collect();
+
+        cells
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let last = row.len().saturating_sub(1);
+                let spans: Vec<Span> = row
+                    .into_iter()
+                    .enumerate()
+                    .map(|(column, text)| {
+                        let text: String = text.chars().take(MAX_COLUMN_WIDTH).collect();
+                        let text = match column == last {
+                            true => text,
+                            false => format!("{:width$}  ", text, width = widths[column]),
+                        };
+                        let color = colors
+                            .field_color(columns[column])
+                            .and_then(|color| Color::from_str(color).ok())
+                            .unwrap_or(Color::White);
+                        Span::styled(text, Style::default().fg(color))
+                    })
+                    .collect();
+                let line = Line::from(spans);
+                ListItem::new(match self.current == Some(index) {
+                    true => line.patch_style(Style::default().add_modifier(Modifier::BOLD)),
+                    false => line,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Builds a queue of the named files with the item at `current` playing.
+    fn queue(names: &[&str], current: Option<usize>) -> PlayQueue {
+        let mut queue = PlayQueue::new();
+        for name in names {
+            queue.push(FileMetadata::with_file_only(Path::new(name)));
+        }
+        queue.current = current;
+        queue.plan_upcoming();
+        queue
+    }
+
+    fn names(queue: &PlayQueue) -> Vec<&str> {
+        queue
+            .items
+            .iter()
+            .map(|item| item.raw_file.as_str())
+            .collect()
+    }
+
+    fn current_name(queue: &PlayQueue) -> Option<&str> {
+        queue.current().map(|item| item.raw_file.as_str())
+    }
+
+    #[test]
+    fn insert_keeps_the_current_item() {
+        let mut queue = queue(&["a", "b", "c"], Some(1));
+
+        assert_eq!(
+            queue.insert(0, FileMetadata::with_file_only(Path::new("x"))),
+            0
+        );
+        assert_eq!(names(&queue), ["x", "a", "b", "c"]);
+        assert_eq!(queue.current, Some(2));
+
+        // Inserting after the current item leaves it where it is.
+        queue.insert(3, FileMetadata::with_file_only(Path::new("y")));
+        assert_eq!(names(&queue), ["x", "a", "b", "y", "c"]);
+        assert_eq!(current_name(&queue), Some("b"));
+        assert_eq!(queue.next().map(|item| item.raw_file.as_str()), Some("y"));
+
+        // Out of range indices append.
+        assert_eq!(
+            queue.insert(99, FileMetadata::with_file_only(Path::new("z"))),
+            5
+        );
+        assert_eq!(names(&queue), ["x", "a", "b", "y", "c", "z"]);
+    }
+
+    #[test]
+    fn remove_before_and_after_the_current_item() {
+        let mut queue = queue(&["a", "b", "c", "d"], Some(2));
+
+        assert_eq!(
+            queue.remove(0).map(|item| item.raw_file),
+            Some("a".to_string())
+        );
+        assert_eq!(queue.current, Some(1));
+        assert_eq!(current_name(&queue), Some("c"));
+
+        queue.remove(2);
+        assert_eq!(names(&queue), ["b", "c"]);
+        assert_eq!(current_name(&queue), Some("c"));
+
+        assert!(queue.remove(5).is_none());
+        assert_eq!(names(&queue), ["b", "c"]);
+    }
+
+    #[test]
+    fn removing_the_current_item_moves_on_to_the_next() {
+        let mut queue = queue(&["a", "b", "c"], Some(1));
+
+        queue.remove(1);
+        assert_eq!(current_name(&queue), Some("c"));
+
+        // Removing the last item while it plays leaves nothing current.
+        queue.remove(1);
+        assert_eq!(names(&queue), ["a"]);
+        assert_eq!(queue.current, None);
+    }
+
+    #[test]
+    fn move_follows_the_current_item() {
+        let mut queue = queue(&["a", "b", "c", "d"], Some(1));
+
+        // Moving the current item takes the pointer along.
+        queue.move_item(1, 3);
+        assert_eq!(names(&queue), ["a", "c", "d", "b"]);
+        assert_eq!(queue.current, Some(3));
+
+        // Moving an item from after to before the current one shifts it down.
+        queue.move_item(0, 2);
+        assert_eq!(names(&queue), ["c", "d", "a", "b"]);
+        assert_eq!(current_name(&queue), Some("b"));
+
+        queue.move_item(3, 0);
+        assert_eq!(names(&queue), ["b", "c", "d", "a"]);
+        assert_eq!(queue.current, Some(0));
+
+        // Moves across the current item shift it up.
+        queue.move_item(3, 0);
+        assert_eq!(names(&queue), ["a", "b", "c", "d"]);
+        assert_eq!(current_name(&queue), Some("b"));
+
+        // Out of range moves do nothing.
+        queue.move_item(0, 4);
+        assert_eq!(names(&queue), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        let mut queue = queue(&["a", "b", "c"], None);
+        queue.set_current(1);
+        assert!(!queue.played.is_empty());
+
+        queue.clear();
+        assert!(queue.items.is_empty());
+        assert!(queue.played.is_empty());
+        assert_eq!(queue.current, None);
+        assert_eq!(queue.upcoming, None);
+        assert!(queue.current().is_none());
+    }
+}
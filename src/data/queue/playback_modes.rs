@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+/// Repeat behaviour once a track or the playlist ends.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    Off,
+    All,
+    One,
+}
+
+/// How the next track is picked.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Random {
+    Off,
+    Track,
+    Album,
+}
+
+/// Encapsulates the playlist's playback modes.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct PlaybackModes {
+    pub consume: bool,
+    pub random: Random,
+    pub repeat: Repeat,
+}
+
+impl PlaybackModes {
+    pub fn new() -> Self {
+        Self {
+            consume: false,
+            random: Random::Off,
+            repeat: Repeat::Off,
+        }
+    }
+
+    /// Cycles repeat between off, all and one.
+    pub fn cycle_repeat(&mut self) {
+        self.repeat = match self.repeat {
+            Repeat::Off => Repeat::All,
+            Repeat::All => Repeat::One,
+            Repeat::One => Repeat::Off,
+        }
+    }
+
+    /// Cycles random between off, per track and per album.
+    pub fn cycle_random(&mut self) {
+        self.random = match self.random {
+            Random::Off => Random::Track,
+            Random::Track => Random::Album,
+            Random::Album => Random::Off,
+        }
+    }
+
+    /// Toggles consume.
+    pub fn toggle_consume(&mut self) {
+        self.consume = !self.consume;
+    }
+
+    /// Returns the active modes as ncmpcpp-style flags, e.g. "[rzc]", or nothing if none are active.
+    /// r: repeat all, s: repeat one, z: random, Z: album random, c: consume.
+    pub fn flags(&self) -> String {
+        let mut flags = String::new();
+        match self.repeat {
+            Repeat::Off => {}
+            Repeat::All => flags.push('r'),
+            Repeat::One => flags.push('s'),
+        }
+        match self.random {
+            Random::Off => {}
+            Random::Track => flags.push('z'),
+            Random::Album => flags.push('Z'),
+        }
+        if self.consume {
+            flags.push('c');
+        }
+        match flags.is_empty() {
+            true => flags,
+            false => format!("[{}]", flags),
+        }
+    }
+}
@@ -1,78 +1,406 @@
-use crate::{data::metadata::file_metadata::FileMetadata, tui::app::App};
+use crate::{
+    data::{
+        config::ContextPlay,
+        library::scan::{playable_files, sort_album_order},
+        metadata::{cue::tracks_in_files, file_metadata::FileMetadata},
+        playlist::playlist_funcs::is_playlist,
+        queue::{play_queue::EnqueueMode, playback_modes::PlaybackModes, undo::QueueSnapshot},
+    },
+    tui::{
+        app::{App, PLAYABLE},
+        popup::{PendingAction, Popup},
+        render::fs_browser::FileBrowser,
+    },
+};
+use std::{path::PathBuf, time::Duration};
 
 impl App {
-    /// Creates a sink and appends audio if the sink is empty or non-existant.
-    /// Plays the audio and appens the current sink elements if the sink isn't empty.
+    /// Inserts the selected file or directory in front of the current item and plays it.
+    /// Files are played in context instead unless context play is off, and playlists replace the queue.
     /// # Examples
     /// ```
-    /// sink = [1, 2]
+    /// queue = [1, 2]
     /// handle_play(3)
-    /// sink = [3, 1, 2]
+    /// queue = [3, 1, 2]
     pub fn handle_play(&mut self) {
-        if let Some(path) = self.file_browser.entries.get(self.file_browser.selected) {
-            if !path.is_dir() {
-                if self.audio.is_empty() {
-                    self.audio.play(path);
-                    self.meta_manager.update_current(path, true);
-                    self.data = self.meta_manager.current.clone();
-                    self.path_queue.push(path.clone());
-                } else {
-                    self.path_queue.insert(0, path.clone());
-                    self.audio.play(&self.path_queue[0]);
-
-                    self.audio.clear_sink();
-                    self.path_queue
-                        .iter()
-                        .skip(1)
-                        .for_each(|element| self.audio.append(element));
-
-                    self.meta_manager.update_current(path, false);
-                    self.data = self.meta_manager.current.clone();
+        match self.file_browser.entries.get(self.file_browser.selected) {
+            Some(path)
+                if !path.is_dir()
+                    && !is_playlist(path)
+                    && self.config.playback.context_play != ContextPlay::Off =>
+            {
+                self.handle_context_play()
+            }
+            _ => self.enqueue_selected(EnqueueMode::Play),
+        }
+    }
+
+    /// Plays the selected file followed by the playable files after it in the browser, so the folder keeps playing.
+    /// # Examples
+    /// ```
+    /// browser = [1, 2, 3, 4], queue = [a, b]
+    /// handle_context_play(2)
+    /// queue = [2*, 3, 4]           (replace)
+    /// queue = [a, b, 2*, 3, 4]     (preserve)
+    pub fn handle_context_play(&mut self) {
+        let browser = &self.file_browser;
+        let items: Vec<FileMetadata> = (browser.selected..browser.entries.len())
+            .filter(|&index| FileBrowser::is_playable_file(&browser.entries[index], &PLAYABLE))
+            .filter_map(|index| browser.track_at(index))
+            .collect();
+        if items.is_empty() {
+            return;
+        }
+
+        match self.config.playback.context_play {
+            ContextPlay::Preserve => {
+                let first = self.queue.len();
+                self.enqueue(items, EnqueueMode::Append);
+                if self.queue.current != Some(first) {
+                    self.queue.set_current(first);
+                    self.start_current();
                 }
             }
+            _ => self.replace_queue(items),
         }
     }
 
-    /// Creates sink if it's empty (equivalent to handle play).
-    /// Appends sond to the end of the sink is it isn't empty.
+    /// Replaces the queue with `items` and plays the first one.
+    pub fn replace_queue(&mut self, items: Vec<FileMetadata>) {
+        self.undo.record(&self.queue);
+        self.queue.restore(items, Some(0));
+        self.queue.set_current(0);
+        self.start_current();
+    }
+
+    /// Appends the selected file or directory to the end of the queue, playing it if nothing else is.
     /// # Examples
     /// ```
-    /// sink = [1, 2]
+    /// queue = [1, 2]
     /// handle_append(3)
-    /// sink = [1, 2, 3]
+    /// queue = [1, 2, 3]
     pub fn handle_append(&mut self) {
-        if let Some(path) = self.file_browser.entries.get(self.file_browser.selected) {
-            if !path.is_dir() {
-                if self.audio.is_empty() {
-                    self.audio.play(path);
-                    self.meta_manager.update_current(path, true);
-                    self.data = self.meta_manager.current.clone();
-                } else {
-                    self.audio.append(path);
-                    self.meta_manager.queue_metadata(path);
-                }
+        self.enqueue_selected(EnqueueMode::Append);
+    }
+
+    /// Inserts the selected file or directory right after the current item so it plays next.
+    /// # Examples
+    /// ```
+    /// queue = [1*, 2]
+    /// handle_play_next(3)
+    /// queue = [1*, 3, 2]
+    pub fn handle_play_next(&mut self) {
+        self.enqueue_selected(EnqueueMode::Next);
+    }
+
+    /// Enqueues the selected browser entry; directories are walked recursively and playlists are read.
+    /// Asks for confirmation first if a directory holds more files than configured.
+    fn enqueue_selected(&mut self, mode: EnqueueMode) {
+        let Some(path) = self.file_browser.entries.get(self.file_browser.selected) else {
+            return;
+        };
+        if is_playlist(path) {
+            self.load_playlist(&path.clone(), mode);
+            return;
+        }
+        if !path.is_dir() {
+            if let Some(track) = self.file_browser.track_at(self.file_browser.selected) {
+                self.enqueue(vec![track], mode);
+            }
+            return;
+        }
+
+        let files = playable_files(path);
+        if self.config.controls.enqueue_confirm_threshold < files.len() {
+            self.popup = Some(Popup::Confirm {
+                message: format!(
+                    "Queue {} files from {}?",
+                    files.len(),
+                    self.format_display_path(path)
+                ),
+                action: PendingAction::Enqueue(files, mode),
+            });
+        } else {
+            self.enqueue_files(files, mode);
+        }
+    }
+
+    /// Reads the files' metadata (splitting files with CUE sheets into their tracks), sorts them in album order and enqueues them.
+    pub fn enqueue_files(&mut self, files: Vec<PathBuf>, mode: EnqueueMode) {
+        let mut items = tracks_in_files(&files);
+        sort_album_order(&mut items);
+        self.enqueue(items, mode);
+    }
+
+    /// Adds items to the queue and keeps playback in step.
+    pub fn enqueue(&mut self, items: Vec<FileMetadata>, mode: EnqueueMode) {
+        if items.is_empty() {
+            return;
+        }
+        self.undo.record(&self.queue);
+        let first = match mode {
+            EnqueueMode::Play => self.queue.current.unwrap_or(0),
+            EnqueueMode::Next => self.queue.current.map(|current| current + 1).unwrap_or(0),
+            EnqueueMode::Append => self.queue.len(),
+        };
+        for (offset, item) in items.into_iter().enumerate() {
+            match mode {
+                EnqueueMode::Append => self.queue.push(item),
+                _ => self.queue.insert(first + offset, item),
+            };
+        }
+
+        match mode {
+            _ if self.queue.current.is_none() => {
+                self.queue.set_current(first);
+                self.start_current();
             }
-            self.path_queue.push(path.clone());
+            EnqueueMode::Play => {
+                self.queue.set_current(first);
+                self.start_current();
+            }
+            EnqueueMode::Next => {
+                self.queue.upcoming = Some(first);
+                self.refresh_preload();
+            }
+            EnqueueMode::Append => self.refresh_preload(),
         }
     }
 
-    /// Skips the current element in the sink, re-appends the next elements to the sink, and gets the metadata for the new head of the sink.
+    /// Restarts the current track if it has played for a few seconds, otherwise goes back to the previously played track.
+    pub fn handle_previous(&mut self) {
+        let restart_threshold = self.config.playback.restart_threshold_secs;
+        if !self.audio.is_empty() && restart_threshold <= self.audio.sink_pos() {
+            self.audio.seek(Duration::ZERO);
+            return;
+        }
+
+        // The newest history entry is the current track itself.
+        if self
+            .history
+            .items
+            .front()
+            .is_some_and(|last| Some(last.id()) == self.queue.current_id())
+        {
+            self.history.pop();
+        }
+        let Some(previous) = self.history.pop() else {
+            self.audio.seek(Duration::ZERO);
+            return;
+        };
+        self.replay(previous);
+    }
+
+    /// Replays the history entry under the cursor.
+    pub fn handle_history_replay(&mut self) {
+        if let Some(item) = self.history.items.get(self.history.selected).cloned() {
+            self.replay(item);
+        }
+    }
+
+    /// Plays an already played track: jumps to it if it is still queued, otherwise inserts it in front of the current item.
+    fn replay(&mut self, item: FileMetadata) {
+        let before_current = self
+            .queue
+            .current
+            .and_then(|current| current.checked_sub(1))
+            .filter(|&index| self.queue.items[index].id() == item.id());
+        let index = match before_current.or_else(|| {
+            self.queue
+                .items
+                .iter()
+                .position(|queued| queued.id() == item.id())
+        }) {
+            Some(index) => index,
+            None => self.queue.insert(self.queue.current.unwrap_or(0), item),
+        };
+        self.queue.set_current(index);
+        self.start_current();
+    }
+
+    /// Plays the item after the current one, removing the current one if consume is on.
     pub fn handle_skip(&mut self) {
-        if 0 < self.audio.get_len() {
-            self.path_queue.remove(0);
-            self.audio.clear_sink();
-
-            match self.path_queue.get(0) {
-                Some(next_path) => {
-                    self.audio.play(next_path);
-                    self.path_queue
-                        .iter()
-                        .skip(1)
-                        .for_each(|element| self.audio.append(element));
-                    self.data = self.meta_manager.pop_next().unwrap_or(FileMetadata::new());
+        if self.queue.current.is_some() {
+            self.queue.advance(true);
+            self.start_current();
+        }
+    }
+
+    /// Applies a playback mode change and makes sure the sink follows the new next track.
+    pub fn handle_mode_change(&mut self, change: fn(&mut PlaybackModes)) {
+        change(&mut self.queue.modes);
+        self.queue.plan_upcoming();
+        self.refresh_preload();
+    }
+
+    /// Plays the item under the playlist cursor.
+    pub fn handle_jump_play(&mut self) {
+        if self.queue.selected < self.queue.len() {
+            self.queue.set_current(self.queue.selected);
+            self.start_current();
+        }
+    }
+
+    /// Removes the item under the playlist cursor; removing the current item plays the one after it.
+    pub fn handle_remove(&mut self) {
+        if self.queue.len() <= self.queue.selected {
+            return;
+        }
+        self.undo.record(&self.queue);
+        let was_current = self.queue.current == Some(self.queue.selected);
+        self.queue.remove(self.queue.selected);
+        self.queue.sync_selection();
+        if was_current {
+            self.start_current();
+        } else {
+            self.refresh_preload();
+        }
+    }
+
+    /// Moves the item under the playlist cursor up one place, taking the cursor with it.
+    pub fn handle_move_up(&mut self) {
+        let selected = self.queue.selected;
+        if 0 < selected && selected < self.queue.len() {
+            self.undo.record(&self.queue);
+            self.queue.move_item(selected, selected - 1);
+            self.queue.selected -= 1;
+            self.queue.sync_selection();
+            self.refresh_preload();
+        }
+    }
+
+    /// Moves the item under the playlist cursor down one place, taking the cursor with it.
+    pub fn handle_move_down(&mut self) {
+        let selected = self.queue.selected;
+        if selected + 1 < self.queue.len() {
+            self.undo.record(&self.queue);
+            self.queue.move_item(selected, selected + 1);
+            self.queue.selected += 1;
+            self.queue.sync_selection();
+            self.refresh_preload();
+        }
+    }
+
+    /// Shuffles the items after the current one.
+    pub fn handle_shuffle(&mut self) {
+        self.undo.record(&self.queue);
+        self.queue.shuffle_remaining();
+        self.refresh_preload();
+    }
+
+    /// Empties the queue and stops playback.
+    pub fn handle_clear(&mut self) {
+        if self.queue.len() == 0 {
+            return;
+        }
+        self.undo.record(&self.queue);
+        self.queue.clear();
+        self.start_current();
+    }
+
+    /// Restores the queue as it was before the last edit.
+    pub fn handle_undo(&mut self) {
+        if let Some(snapshot) = self.undo.undo(&self.queue) {
+            self.restore_snapshot(snapshot);
+        }
+    }
+
+    /// Re-applies the last undone edit.
+    pub fn handle_redo(&mut self) {
+        if let Some(snapshot) = self.undo.redo(&self.queue) {
+            self.restore_snapshot(snapshot);
+        }
+    }
+
+    /// Puts a snapshot back into the queue.
+    /// If the playing track is part of it, playback carries on untouched; otherwise the snapshot's current item starts.
+    fn restore_snapshot(&mut self, snapshot: QueueSnapshot) {
+        let playing = self.queue.current_id().filter(|_| !self.audio.is_empty());
+        let still_queued = playing.as_ref().and_then(|playing| {
+            snapshot
+                .current
+                .filter(|&index| {
+                    snapshot.items.get(index).map(FileMetadata::id).as_ref() == Some(playing)
+                })
+                .or_else(|| snapshot.items.iter().position(|item| &item.id() == playing))
+        });
+
+        match still_queued {
+            Some(index) => {
+                self.queue.restore(snapshot.items, Some(index));
+                self.refresh_preload();
+            }
+            None => {
+                self.queue.restore(snapshot.items, snapshot.current);
+                self.start_current();
+            }
+        }
+        self.queue.sync_selection();
+    }
+
+    /// Starts the queue's current item on a fresh sink, or stops playback if there is none.
+    pub fn start_current(&mut self) {
+        self.load_current(false);
+    }
+
+    /// Loads the queue's current item on a fresh sink, playing it unless `paused`.
+    pub fn load_current(&mut self, paused: bool) {
+        self.preloaded = None;
+        match self.queue.current() {
+            Some(item) => {
+                let item = item.clone();
+                self.audio.play(&item.file_path, item.cue, paused);
+                self.history.push(item);
+            }
+            None => self.audio.clear_sink(),
+        }
+    }
+
+    /// Keeps the sink in step with the queue.
+    /// Advances the queue when a track ends and preloads the next item so tracks play back to back.
+    pub fn sync_playback(&mut self) {
+        if self.queue.current.is_none() {
+            return;
+        }
+
+        match self.audio.get_len() {
+            // The current track ended and nothing was preloaded (or the file failed to load).
+            0 => {
+                self.queue.advance(false);
+                self.start_current();
+            }
+            // The current track ended and the preloaded one took over.
+            1 if self.preloaded.is_some() => {
+                if let Some(item) = self.queue.advance(false) {
+                    self.history.push(item.clone());
+                }
+                self.preloaded = None;
+            }
+            1 => {
+                if let Some(next) = self.queue.next().cloned()
+                    && self.audio.append(&next.file_path, next.cue)
+                {
+                    self.preloaded = Some(next.id());
                 }
-                None => self.data = FileMetadata::new(),
             }
+            _ => {}
+        }
+    }
+
+    /// Swaps the preloaded track if it no longer matches what the queue will play next.
+    /// The current track plays on untouched.
+    pub fn refresh_preload(&mut self) {
+        let next_id = self.queue.next_id();
+        if self.preloaded == next_id {
+            return;
+        }
+
+        let next = self.queue.next().cloned();
+        let next = next
+            .as_ref()
+            .map(|next| (next.file_path.as_path(), next.cue));
+        if let Some(waiting) = self.audio.replace_next(next) {
+            self.preloaded = next_id.filter(|_| waiting);
         }
     }
 }
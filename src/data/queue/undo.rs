@@ -0,0 +1,61 @@
+use crate::data::{metadata::file_metadata::FileMetadata, queue::play_queue::PlayQueue};
+use std::collections::VecDeque;
+
+/// A copy of the queue's contents at one point in time.
+#[derive(Clone)]
+pub struct QueueSnapshot {
+    pub current: Option<usize>,
+    pub items: Vec<FileMetadata>,
+}
+
+impl QueueSnapshot {
+    pub fn of(queue: &PlayQueue) -> Self {
+        Self {
+            current: queue.current,
+            items: queue.items.clone(),
+        }
+    }
+}
+
+/// Encapsulates the undo and redo stacks for queue edits.
+pub struct UndoHistory {
+    pub depth: usize,
+    pub redo: Vec<QueueSnapshot>,
+    pub undo: VecDeque<QueueSnapshot>,
+}
+
+impl UndoHistory {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            redo: Vec::new(),
+            undo: VecDeque::new(),
+        }
+    }
+
+    /// Records the queue before an edit; a new edit drops everything that could be redone.
+    pub fn record(&mut self, queue: &PlayQueue) {
+        if self.depth == 0 {
+            return;
+        }
+        self.undo.push_back(QueueSnapshot::of(queue));
+        if self.depth < self.undo.len() {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    /// Returns the snapshot to restore for an undo, saving the present queue for redo.
+    pub fn undo(&mut self, queue: &PlayQueue) -> Option<QueueSnapshot> {
+        let snapshot = self.undo.pop_back()?;
+        self.redo.push(QueueSnapshot::of(queue));
+        Some(snapshot)
+    }
+
+    /// Returns the snapshot to restore for a redo, saving the present queue for undo.
+    pub fn redo(&mut self, queue: &PlayQueue) -> Option<QueueSnapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push_back(QueueSnapshot::of(queue));
+        Some(snapshot)
+    }
+}
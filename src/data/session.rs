@@ -0,0 +1,83 @@
+use crate::{
+    data::metadata::cache::save_metadata_cache,
+    tui::app::{App, Tab},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fs, io, path::PathBuf};
+
+/// Everything besides the queues that is restored on the next launch.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub current_dir: Option<PathBuf>,
+    pub sel_map: HashMap<PathBuf, usize>,
+    pub tab: Tab,
+    pub volume: i16,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            current_dir: None,
+            sel_map: HashMap::new(),
+            tab: Tab::Browser,
+            volume: 100,
+        }
+    }
+}
+
+/// Returns the path of a file in rmpr's data directory.
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|mut path| {
+        path.push("rmpr");
+        path.push(name);
+        path
+    })
+}
+
+/// Loads the saved session, if there is a readable one.
+pub fn load_session() -> Option<Session> {
+    let content = fs::read_to_string(data_file("session.toml")?).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Writes a file to rmpr's data directory, creating the directory if needed.
+pub fn write_data_file(name: &str, content: &str) -> io::Result<()> {
+    let Some(path) = data_file(name) else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+impl App {
+    /// Puts back the volume, tab and browser location of a saved session.
+    pub fn restore_session(&mut self, session: Session) {
+        self.audio.set_volume(session.volume);
+        self.tab = session.tab;
+        if let Tab::Saved = self.tab {
+            self.refresh_saved_playlists();
+        }
+        self.file_browser.sel_map = session.sel_map;
+        if let Some(dir) = session.current_dir.filter(|dir| dir.is_dir()) {
+            self.file_browser.selected = *self.file_browser.sel_map.get(&dir).unwrap_or(&0);
+            self.file_browser.current_dir = dir;
+        }
+    }
+
+    /// Saves the queues, the session and the metadata cache so the next launch picks up where this one is.
+    pub fn save_session(&mut self) -> Result<(), Box<dyn Error>> {
+        self.save_queues()?;
+        save_metadata_cache()?;
+        let session = Session {
+            current_dir: Some(self.file_browser.current_dir.clone()),
+            sel_map: self.file_browser.sel_map.clone(),
+            tab: self.tab,
+            volume: self.audio.vol,
+        };
+        write_data_file("session.toml", &toml::to_string(&session)?)?;
+        Ok(())
+    }
+}
@@ -1,47 +1,85 @@
-use crate::handlers::sink_handler::SinkHandler;
+use crate::{
+    data::{
+        config::{Playback, Preview},
+        metadata::cue::CueRange,
+    },
+    handlers::sink_handler::SinkHandler,
+};
 use rodio::OutputStream;
-use std::{error::Error, path::PathBuf, sync::Arc, thread};
+use std::{error::Error, path::Path, sync::Arc, time::Duration};
 
 /// Encapsulates audio-related state and controls.
 pub struct InputHandler {
     pub audio_player: Arc<SinkHandler>,
     pub paused: bool,
+    pub previewing: bool,
     pub _stream: OutputStream,
     pub vol: i16,
 }
 
 impl InputHandler {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
+    pub fn new(playback: Playback) -> Result<Self, Box<dyn Error>> {
         let (stream, stream_handle) = OutputStream::try_default()?;
-        let audio_player = Arc::new(SinkHandler::new(stream_handle));
+        let audio_player = Arc::new(SinkHandler::new(stream_handle, playback));
         Ok(Self {
             audio_player,
             paused: false,
+            previewing: false,
             _stream: stream,
             vol: 100,
         })
     }
 
-    /// Starts playing the file on a new thread using the AudioPlayer.
-    pub fn play(&mut self, path: &PathBuf) {
-        let path_clone = path.clone();
-        let current_vol = self.vol;
-        let sink_handler = Arc::clone(&self.audio_player);
-        thread::spawn(move || {
-            if let Err(e) = sink_handler.play_file(path_clone, current_vol) {
-                eprintln!("Failed to play file: {}", e);
+    /// Starts playing the file (or the range of it) on a fresh sink using the AudioPlayer,
+    /// or loads it without playing if `paused`.
+    /// This runs on the caller's thread so the sink is ready before the queue is synced against it.
+    pub fn play(&mut self, path: &Path, range: Option<CueRange>, paused: bool) {
+        if let Err(e) = self
+            .audio_player
+            .play_file(path.to_path_buf(), range, self.vol, paused)
+        {
+            eprintln!("Failed to play file: {}", e);
+        }
+        self.paused = paused;
+    }
+
+    /// Append audio (or the range of it) to the sink, returning true if it was queued.
+    pub fn append(&mut self, path: &Path, range: Option<CueRange>) -> bool {
+        match self
+            .audio_player
+            .append_to_sink(path.to_path_buf(), range, self.vol)
+        {
+            Ok(appended) => appended,
+            Err(e) => {
+                eprintln!("Failed to append file to sink: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Swaps the appended track that hasn't started yet for the file (or the range of it), or for nothing.
+    /// Returns None if no appended track was waiting, otherwise whether a track waits now.
+    pub fn replace_next(&mut self, next: Option<(&Path, Option<CueRange>)>) -> Option<bool> {
+        let source = match next {
+            Some((path, range)) => {
+                match self.audio_player.track_source(path.to_path_buf(), range) {
+                    Ok(source) => Some(source),
+                    Err(e) => {
+                        eprintln!("Failed to append file to sink: {}", e);
+                        None
+                    }
+                }
             }
-        });
-        self.paused = false;
+            None => None,
+        };
+        let waiting = source.is_some();
+        self.audio_player.replace_next(source).then_some(waiting)
     }
 
-    /// Append audio to the sink.
-    pub fn append(&mut self, path: &PathBuf) {
-        let path_clone = path.clone();
-        let current_vol = self.vol;
-        let sink_handler = Arc::clone(&self.audio_player);
-        if let Err(e) = sink_handler.append_to_sink(path_clone, current_vol) {
-            eprintln!("Failed to append file to sink: {}", e);
+    /// Seeks the current track to `pos`.
+    pub fn seek(&self, pos: Duration) {
+        if let Err(e) = self.audio_player.seek(pos) {
+            eprintln!("Failed to seek: {}", e);
         }
     }
 
@@ -57,12 +95,63 @@ impl InputHandler {
     }
 
     /// Adjusts the volume by a given delta.
+    /// While previewing the preview gets the new volume and the ducked main sink is left alone.
     pub fn adjust_volume(&mut self, delta: i16) {
         let new_vol = self.vol + delta;
-        if 0 <= new_vol && new_vol <= 100 {
+        if (0..=100).contains(&new_vol) {
             self.vol = new_vol;
-            self.audio_player.set_volume(self.vol);
+            if self.previewing {
+                self.audio_player.set_preview_volume(self.vol);
+            } else {
+                self.audio_player.set_volume(self.vol);
+            }
+        }
+    }
+
+    /// Sets the volume, clamped to 0..=100.
+    pub fn set_volume(&mut self, vol: i16) {
+        self.vol = vol.clamp(0, 100);
+        self.audio_player.set_volume(self.vol);
+    }
+
+    /// Starts previewing the file, pausing or ducking the main sink as configured.
+    pub fn start_preview(&mut self, path: &Path, preview: &Preview, duration_hint: Option<f64>) {
+        self.audio_player.stop_preview();
+        if let Err(e) = self.audio_player.play_preview(
+            path.to_path_buf(),
+            preview.offset,
+            Duration::from_secs(preview.length_secs),
+            duration_hint,
+            self.vol,
+        ) {
+            eprintln!("Failed to preview file: {}", e);
+            return;
+        }
+        if !self.previewing {
+            if preview.pause_main {
+                self.audio_player.set_paused(true);
+            } else {
+                self.audio_player
+                    .set_volume(self.vol * preview.duck_volume / 100);
+            }
         }
+        self.previewing = true;
+    }
+
+    /// Stops the preview and restores the main sink.
+    pub fn stop_preview(&mut self) {
+        if !self.previewing {
+            return;
+        }
+        self.audio_player.stop_preview();
+        self.audio_player.set_paused(self.paused);
+        self.audio_player.set_volume(self.vol);
+        self.previewing = false;
+    }
+
+    /// Returns true if a preview was started and has played to its end.
+    pub fn preview_finished(&self) -> bool {
+        self.previewing && !self.audio_player.preview_playing()
     }
 
     /// Returns the sink's position in seconds.
@@ -1,2 +1,5 @@
 pub mod input_handler;
+pub mod next_track;
+pub mod silence_skip;
 pub mod sink_handler;
+pub mod track_range;
@@ -0,0 +1,147 @@
+use rodio::{Sample, Source, source::SeekError};
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// What a `NextTrack` holds until it starts playing.
+enum Slot<S> {
+    Waiting(Option<S>),
+    Started,
+}
+
+/// A queued source that can be swapped for another (or for nothing) until it starts playing,
+/// so the track after the current one can change without touching the current one.
+pub struct NextTrack<S> {
+    slot: Arc<Mutex<Slot<S>>>,
+    source: Option<S>,
+    started: bool,
+}
+
+/// Swaps the source of a `NextTrack` that hasn't started yet.
+pub struct NextTrackHandle<S> {
+    slot: Arc<Mutex<Slot<S>>>,
+}
+
+impl<S> NextTrack<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    pub fn new(source: S) -> (Self, NextTrackHandle<S>) {
+        let slot = Arc::new(Mutex::new(Slot::Waiting(Some(source))));
+        let track = Self {
+            slot: Arc::clone(&slot),
+            source: None,
+            started: false,
+        };
+        (track, NextTrackHandle { slot })
+    }
+
+    /// Takes the waiting source for good, once the sink gets to it.
+    fn start(&mut self) -> Option<&mut S> {
+        if !self.started {
+            self.started = true;
+            let slot = mem::replace(
+                &mut *self.slot.lock().expect("Mutex poisoned"),
+                Slot::Started,
+            );
+            if let Slot::Waiting(source) = slot {
+                self.source = source;
+            }
+        }
+        self.source.as_mut()
+    }
+
+    /// Looks at the source that will play, whether or not it has started.
+    fn peek<T>(&self, f: impl FnOnce(&S) -> T) -> Option<T> {
+        if self.started {
+            return self.source.as_ref().map(f);
+        }
+        match &*self.slot.lock().expect("Mutex poisoned") {
+            Slot::Waiting(Some(source)) => Some(f(source)),
+            _ => None,
+        }
+    }
+}
+
+impl<S> NextTrackHandle<S> {
+    /// Replaces the waiting source, returning false if it has already started.
+    pub fn swap(&self, source: Option<S>) -> bool {
+        match &mut *self.slot.lock().expect("Mutex poisoned") {
+            Slot::Waiting(waiting) => {
+                *waiting = source;
+                true
+            }
+            Slot::Started => false,
+        }
+    }
+}
+
+impl<S> Iterator for NextTrack<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        self.start()?.next()
+    }
+}
+
+impl<S> Source for NextTrack<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.peek(S::current_frame_len).unwrap_or(Some(0))
+    }
+
+    fn channels(&self) -> u16 {
+        self.peek(S::channels).unwrap_or(1)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.peek(S::sample_rate).unwrap_or(44100)
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.peek(S::total_duration).flatten()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        match self.start() {
+            Some(source) => source.try_seek(pos),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn swaps_until_started() {
+        let (mut track, handle) = NextTrack::new(SamplesBuffer::new(1, 8000, vec![1i16; 4]));
+        assert!(handle.swap(Some(SamplesBuffer::new(1, 8000, vec![2i16; 2]))));
+        assert_eq!(track.channels(), 1);
+
+        assert_eq!(track.next(), Some(2));
+        assert!(!handle.swap(None));
+        assert_eq!(track.collect::<Vec<_>>(), [2]);
+    }
+
+    #[test]
+    fn swapped_for_nothing_ends_at_once() {
+        let (mut track, handle) = NextTrack::new(SamplesBuffer::new(1, 8000, vec![1i16; 4]));
+        assert!(handle.swap(None));
+
+        assert_eq!(track.current_frame_len(), Some(0));
+        assert_eq!(track.next(), None);
+    }
+}
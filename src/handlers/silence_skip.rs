@@ -0,0 +1,411 @@
+use crate::data::config::Playback;
+use rodio::{Sample, Source, source::SeekError};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// Where the skipper dropped audio, so a played position can be mapped back to the file.
+///
+/// Each mark holds the output position (in milliseconds) from which a total amount of
+/// skipped audio applies; the position only jumps once the audio around the skip plays.
+pub struct SkipLog {
+    marks: Mutex<Vec<(u64, u64)>>,
+}
+
+impl SkipLog {
+    pub fn new() -> Self {
+        Self {
+            marks: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn marks(&self) -> MutexGuard<'_, Vec<(u64, u64)>> {
+        self.marks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Forgets every skip, e.g. when a new source starts or after a seek.
+    pub fn clear(&self) {
+        self.marks().clear();
+    }
+
+    /// Records that `total_millis` were skipped once the output reaches `at_millis`.
+    fn record(&self, at_millis: u64, total_millis: u64) {
+        let mut marks = self.marks();
+        match marks.last_mut() {
+            Some(last) if last.0 == at_millis => last.1 = total_millis,
+            _ => marks.push((at_millis, total_millis)),
+        }
+    }
+
+    /// Returns how much audio was skipped before `played` of output.
+    pub fn skipped_at(&self, played: Duration) -> Duration {
+        let played = played.as_millis() as u64;
+        let skipped = self
+            .marks()
+            .iter()
+            .take_while(|(at, _)| *at <= played)
+            .last()
+            .map(|(_, total)| *total)
+            .unwrap_or(0);
+        Duration::from_millis(skipped)
+    }
+}
+
+/// Wraps a source and drops the silence at its start and end, optionally speeding through long silent gaps.
+///
+/// Dropped audio is recorded in the shared `SkipLog` so the displayed position can still match
+/// the position in the file.
+pub struct SilenceSkip<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    input: S,
+    threshold: f32,
+    max_gap_frames: usize,
+    gap_speed: usize,
+    skip_gaps: bool,
+    started: bool,
+    reset_pending: bool,
+    emitted: u64,
+    frame: Vec<S::Item>,
+    held: VecDeque<S::Item>,
+    held_extra: usize,
+    out: VecDeque<S::Item>,
+    zeros: usize,
+    tail: VecDeque<S::Item>,
+    skipped_frames: u64,
+    log: Arc<SkipLog>,
+}
+
+impl<S> SilenceSkip<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    pub fn new(input: S, playback: &Playback, log: Arc<SkipLog>) -> Self {
+        let max_gap_frames =
+            (input.sample_rate() as u64 * playback.silence_min_ms / 1000).max(1) as usize;
+        Self {
+            input,
+            threshold: 10f32.powf(playback.silence_threshold_db / 20.0),
+            max_gap_frames,
+            gap_speed: playback.silence_gap_speed.max(1) as usize,
+            skip_gaps: playback.silence_skip_gaps,
+            started: false,
+            reset_pending: true,
+            emitted: 0,
+            frame: Vec::new(),
+            held: VecDeque::new(),
+            held_extra: 0,
+            out: VecDeque::new(),
+            zeros: 0,
+            tail: VecDeque::new(),
+            skipped_frames: 0,
+            log,
+        }
+    }
+
+    /// Converts frames to milliseconds at the input's sample rate.
+    fn frames_to_millis(&self, frames: u64) -> u64 {
+        frames * 1000 / self.input.sample_rate().max(1) as u64
+    }
+
+    /// Records dropped frames; they count from the point where the output queued so far ends.
+    fn skip_frames(&mut self, frames: usize) {
+        if frames == 0 {
+            return;
+        }
+        self.skipped_frames += frames as u64;
+        let channels = self.input.channels().max(1) as u64;
+        let queued = (self.out.len() + self.zeros + self.tail.len()) as u64;
+        let at = self.frames_to_millis((self.emitted + queued) / channels);
+        let total = self.frames_to_millis(self.skipped_frames);
+        self.log.record(at, total);
+    }
+
+    /// Reads one frame (one sample per channel) into `self.frame`, returning false at the end of the input.
+    fn read_frame(&mut self) -> bool {
+        let channels = self.input.channels().max(1) as usize;
+        self.frame.clear();
+        while self.frame.len() < channels {
+            match self.input.next() {
+                Some(sample) => self.frame.push(sample),
+                None => break,
+            }
+        }
+        !self.frame.is_empty()
+    }
+
+    /// Returns true if every sample in the current frame is below the threshold.
+    fn frame_is_silent(&self) -> bool {
+        self.frame
+            .iter()
+            .all(|sample| sample.to_f32().abs() < self.threshold)
+    }
+
+    /// Returns the next sample to play, reading ahead through silence as needed.
+    fn next_sample(&mut self) -> Option<S::Item> {
+        loop {
+            if let Some(sample) = self.out.pop_front() {
+                return Some(sample);
+            }
+            if 0 < self.zeros {
+                self.zeros -= 1;
+                return Some(S::Item::zero_value());
+            }
+            if let Some(sample) = self.tail.pop_front() {
+                return Some(sample);
+            }
+
+            if !self.read_frame() {
+                // Whatever silence is still held is trailing silence.
+                let trailing = self.held.len() / self.input.channels().max(1) as usize;
+                self.held.clear();
+                self.skip_frames(trailing + self.held_extra);
+                self.held_extra = 0;
+                return None;
+            }
+
+            let silent = self.frame_is_silent();
+
+            if !self.started {
+                if silent {
+                    self.skip_frames(1);
+                    continue;
+                }
+                self.started = true;
+            }
+
+            if silent {
+                if self.held.len() < self.max_gap_frames * self.frame.len() {
+                    self.held.extend(self.frame.drain(..));
+                } else {
+                    self.held_extra += 1;
+                }
+                continue;
+            }
+
+            // Audible again, so the held silence was a gap and gets played back.
+            self.out.extend(self.held.drain(..));
+            if 0 < self.held_extra {
+                // The rest of a long gap plays as silence, sped up if gaps are skipped.
+                let played = match self.skip_gaps {
+                    true => self.held_extra / self.gap_speed,
+                    false => self.held_extra,
+                };
+                self.zeros = played * self.frame.len();
+                self.skip_frames(self.held_extra - played);
+                self.held_extra = 0;
+            }
+            self.tail.extend(self.frame.drain(..));
+        }
+    }
+}
+
+impl<S> Iterator for SilenceSkip<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        if self.reset_pending {
+            self.reset_pending = false;
+            self.log.clear();
+        }
+
+        let sample = self.next_sample()?;
+        self.emitted += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for SilenceSkip<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        // After a seek the sink reports the real file position, so nothing is skipped anymore.
+        self.started = true;
+        self.held.clear();
+        self.held_extra = 0;
+        self.out.clear();
+        self.zeros = 0;
+        self.tail.clear();
+        self.skipped_frames = 0;
+        self.log.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::metadata::cue::CueRange, handlers::track_range::TrackRange};
+    use rodio::{Decoder, buffer::SamplesBuffer};
+    use std::io::Cursor;
+
+    const RATE: u32 = 1000;
+    const TONE: f32 = 0.5;
+
+    /// Builds a mono buffer from (length in frames, audible) sections.
+    fn sections(parts: &[(usize, bool)]) -> Vec<f32> {
+        parts
+            .iter()
+            .flat_map(|&(frames, audible)| vec![if audible { TONE } else { 0.0 }; frames])
+            .collect()
+    }
+
+    fn playback(min_ms: u64, skip_gaps: bool) -> Playback {
+        Playback {
+            silence_gap_speed: 4,
+            silence_min_ms: min_ms,
+            silence_skip: true,
+            silence_skip_gaps: skip_gaps,
+            ..Playback::default()
+        }
+    }
+
+    fn run(channels: u16, samples: Vec<f32>, playback: &Playback) -> (Vec<f32>, Arc<SkipLog>) {
+        let log = Arc::new(SkipLog::new());
+        let source = SamplesBuffer::new(channels, RATE, samples);
+        let output = SilenceSkip::new(source, playback, Arc::clone(&log)).collect();
+        (output, log)
+    }
+
+    #[test]
+    fn trims_leading_and_trailing_silence() {
+        let samples = sections(&[
+            (500, false),
+            (300, true),
+            (400, false),
+            (200, true),
+            (600, false),
+        ]);
+        let (output, log) = run(1, samples, &playback(1000, false));
+
+        // The 400 ms gap is shorter than the minimum, so it plays untouched.
+        assert_eq!(output, sections(&[(300, true), (400, false), (200, true)]));
+        assert_eq!(log.skipped_at(Duration::ZERO), Duration::from_millis(500));
+        assert_eq!(
+            log.skipped_at(Duration::from_millis(899)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            log.skipped_at(Duration::from_millis(900)),
+            Duration::from_millis(1100)
+        );
+    }
+
+    #[test]
+    fn plays_long_gaps_whole_without_gap_skipping() {
+        let samples = sections(&[(300, true), (400, false), (200, true)]);
+        let (output, log) = run(1, samples.clone(), &playback(100, false));
+
+        assert_eq!(output, samples);
+        assert_eq!(log.skipped_at(Duration::from_millis(900)), Duration::ZERO);
+    }
+
+    #[test]
+    fn speeds_through_long_gaps() {
+        let samples = sections(&[(300, true), (400, false), (200, true)]);
+        let (output, log) = run(1, samples, &playback(100, true));
+
+        // 100 ms of the gap is kept, the other 300 ms play 4 times faster.
+        assert_eq!(output, sections(&[(300, true), (175, false), (200, true)]));
+        // The skipped time only counts once the audio after the gap plays.
+        assert_eq!(log.skipped_at(Duration::from_millis(474)), Duration::ZERO);
+        assert_eq!(
+            log.skipped_at(Duration::from_millis(475)),
+            Duration::from_millis(225)
+        );
+    }
+
+    #[test]
+    fn frames_are_silent_only_if_every_channel_is() {
+        // The left channel starts 100 frames before the right one.
+        let mut samples = Vec::new();
+        for frame in 0..400 {
+            samples.push(if 100 <= frame { TONE } else { 0.0 });
+            samples.push(if 200 <= frame { TONE } else { 0.0 });
+        }
+        let (output, log) = run(2, samples, &playback(1000, false));
+
+        assert_eq!(output.len(), 300 * 2);
+        assert_eq!(output[..2], [TONE, 0.0]);
+        assert_eq!(log.skipped_at(Duration::ZERO), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn silent_input_is_dropped_entirely() {
+        let (output, log) = run(1, sections(&[(1000, false)]), &playback(100, false));
+
+        assert!(output.is_empty());
+        assert_eq!(log.skipped_at(Duration::ZERO), Duration::from_millis(1000));
+    }
+
+    /// Builds a 16 bit mono WAV at `RATE` from (length in frames, audible) sections.
+    fn wav(parts: &[(usize, bool)]) -> Vec<u8> {
+        let data: Vec<u8> = sections(parts)
+            .into_iter()
+            .flat_map(|sample| ((sample * i16::MAX as f32) as i16).to_le_bytes())
+            .collect();
+        let mut wav = b"RIFF".to_vec();
+        wav.extend((36 + data.len() as u32).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(RATE.to_le_bytes());
+        wav.extend((RATE * 2).to_le_bytes());
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend((data.len() as u32).to_le_bytes());
+        wav.extend(data);
+        wav
+    }
+
+    #[test]
+    fn trims_a_decoded_cue_track() {
+        let file = wav(&[(500, false), (300, true), (600, false)]);
+        let decoder = Decoder::new(Cursor::new(file)).unwrap();
+        // The track starts 200 ms into the file and ends 200 ms before its end.
+        let range = CueRange {
+            start: 0.2,
+            end: Some(1.2),
+        };
+        let log = Arc::new(SkipLog::new());
+        let source = TrackRange::new(decoder, Some(range));
+        let output: Vec<i16> =
+            SilenceSkip::new(source, &playback(1000, false), Arc::clone(&log)).collect();
+
+        assert_eq!(output.len(), 300);
+        assert!(output.iter().all(|&sample| sample != 0));
+        assert_eq!(log.skipped_at(Duration::ZERO), Duration::from_millis(300));
+    }
+}
@@ -1,10 +1,20 @@
-use rodio::{Decoder, OutputStreamHandle, PlayError, Sink, decoder::DecoderError};
+use crate::{
+    data::{config::Playback, metadata::cue::CueRange},
+    handlers::{
+        next_track::{NextTrack, NextTrackHandle},
+        silence_skip::{SilenceSkip, SkipLog},
+        track_range::TrackRange,
+    },
+};
+use rodio::{
+    Decoder, OutputStreamHandle, PlayError, Sink, Source, decoder::DecoderError, source::SeekError,
+};
 use std::{
     fmt,
     fs::File,
     io::{BufReader, Error},
     path::PathBuf,
-    sync::Mutex,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -13,6 +23,7 @@ pub enum AudioError {
     Io(Error),
     Decoder(DecoderError),
     Play(PlayError),
+    Seek(SeekError),
 }
 
 impl fmt::Display for AudioError {
@@ -21,37 +32,140 @@ impl fmt::Display for AudioError {
             AudioError::Io(e) => write!(f, "IO error: {}", e),
             AudioError::Decoder(e) => write!(f, "Decoder error: {}", e),
             AudioError::Play(e) => write!(f, "Stream error: {}", e),
+            AudioError::Seek(e) => write!(f, "Seek error: {}", e),
         }
     }
 }
 
+/// A decoded track, limited to its range and wrapped in the silence skipper if enabled.
+pub type TrackSource = Box<dyn Source<Item = i16> + Send>;
+
 /// Encapsulates an audio sink and an output stream handle.
 pub struct SinkHandler {
+    next: Mutex<Option<NextTrackHandle<TrackSource>>>,
+    playback: Playback,
+    preview: Mutex<Option<Sink>>,
     sink: Mutex<Option<Sink>>,
+    skips: Arc<SkipLog>,
     stream_handle: OutputStreamHandle,
 }
 
 impl SinkHandler {
-    pub fn new(stream_handle: OutputStreamHandle) -> Self {
+    pub fn new(stream_handle: OutputStreamHandle, playback: Playback) -> Self {
         Self {
+            next: Mutex::new(None),
+            playback,
+            preview: Mutex::new(None),
             stream_handle,
             sink: Mutex::new(None),
+            skips: Arc::new(SkipLog::new()),
         }
     }
 
-    /// Plays the given file and sets its volume.
-    pub fn play_file(&self, path: PathBuf, vol: i16) -> Result<(), AudioError> {
+    /// Decodes the file, limited to `range` and wrapped in the silence skipper if enabled.
+    pub fn track_source(
+        &self,
+        path: PathBuf,
+        range: Option<CueRange>,
+    ) -> Result<TrackSource, AudioError> {
         let file = File::open(path).map_err(AudioError::Io)?;
         let reader = BufReader::new(file);
         let source = Decoder::new(reader).map_err(AudioError::Decoder)?;
+        let source = TrackRange::new(source, range);
+        Ok(match self.playback.silence_skip {
+            true => Box::new(SilenceSkip::new(
+                source,
+                &self.playback,
+                Arc::clone(&self.skips),
+            )),
+            false => Box::new(source),
+        })
+    }
+
+    /// Plays the given file (or the range of it) and sets its volume.
+    /// A `paused` sink is paused before anything is appended, so not a sample plays.
+    pub fn play_file(
+        &self,
+        path: PathBuf,
+        range: Option<CueRange>,
+        vol: i16,
+        paused: bool,
+    ) -> Result<(), AudioError> {
+        let source = self.track_source(path, range)?;
         let sink = Sink::try_new(&self.stream_handle).map_err(AudioError::Play)?;
+        if paused {
+            sink.pause();
+        }
 
+        self.skips.clear();
         sink.append(source);
         *self.sink.lock().expect("Mutex poisoned") = Some(sink);
+        *self.next.lock().expect("Mutex poisoned") = None;
         self.set_volume(vol);
         Ok(())
     }
 
+    /// Plays `length` of the given file on a separate preview sink, starting `offset` (0.0 - 1.0) into it.
+    /// `duration_hint` is used when the decoder can't tell the file's length.
+    pub fn play_preview(
+        &self,
+        path: PathBuf,
+        offset: f64,
+        length: Duration,
+        duration_hint: Option<f64>,
+        vol: i16,
+    ) -> Result<(), AudioError> {
+        let file = File::open(path).map_err(AudioError::Io)?;
+        let reader = BufReader::new(file);
+        let mut source = Decoder::new(reader).map_err(AudioError::Decoder)?;
+        let sink = Sink::try_new(&self.stream_handle).map_err(AudioError::Play)?;
+
+        let total = source
+            .total_duration()
+            .or(duration_hint.map(Duration::from_secs_f64));
+        if let Some(total) = total {
+            // Not every format can seek; those previews just start from the beginning.
+            let _ = source.try_seek(total.mul_f64(offset.clamp(0.0, 1.0)));
+        }
+
+        sink.append(source.take_duration(length));
+        sink.set_volume((vol as f32) / 100.0);
+        *self.preview.lock().expect("Mutex poisoned") = Some(sink);
+        Ok(())
+    }
+
+    /// Stops and drops the preview sink.
+    pub fn stop_preview(&self) {
+        if let Some(sink) = self.preview.lock().expect("Mutex poisoned").take() {
+            sink.stop();
+        }
+    }
+
+    /// Returns true while the preview sink still has audio to play.
+    pub fn preview_playing(&self) -> bool {
+        let preview_guard = self.preview.lock().expect("Mutex poisoned");
+        match &*preview_guard {
+            Some(sink) => !sink.empty(),
+            None => false,
+        }
+    }
+
+    /// Sets the preview's volume.
+    pub fn set_preview_volume(&self, mag: i16) {
+        let preview_guard = self.preview.lock().expect("Mutex poisoned");
+        if let Some(ref sink) = *preview_guard {
+            sink.set_volume((mag as f32) / 100.0);
+        }
+    }
+
+    /// Pauses or resumes the sink without toggling.
+    pub fn set_paused(&self, paused: bool) {
+        let sink_guard = self.sink.lock().expect("Mutex poisoned");
+        if let Some(ref sink) = *sink_guard {
+            if paused { sink.pause() } else { sink.play() }
+        }
+    }
+
     /// Toggles play and pause.
     pub fn toggle_play_pause(&self) {
         let sink_guard = self.sink.lock().expect("Mutex poisoned");
@@ -72,41 +186,71 @@ impl SinkHandler {
         }
     }
 
-    /// Gets the sink's position in seconds.
-    pub fn sink_pos(&self) -> u64 {
+    /// Gets the sink's position in the file, including audio dropped by the silence skipper.
+    fn file_pos(&self) -> Duration {
         let sink_guard = self.sink.lock().expect("Mutex poisoned");
         match &*sink_guard {
-            Some(sink) => sink.get_pos().as_secs(),
-            None => Duration::new(0, 0).as_secs(),
+            Some(sink) => {
+                let played = sink.get_pos();
+                played + self.skips.skipped_at(played)
+            }
+            None => Duration::new(0, 0),
         }
     }
 
+    /// Gets the sink's position in seconds.
+    pub fn sink_pos(&self) -> u64 {
+        self.file_pos().as_secs()
+    }
+
     /// Gets the sink's position in milliseconds.
     pub fn sink_pos_millis(&self) -> u128 {
-        let sink_guard = self.sink.lock().expect("Mutex poisoned");
-        match &*sink_guard {
-            Some(sink) => sink.get_pos().as_millis(),
-            None => Duration::new(0, 0).as_millis(),
-        }
+        self.file_pos().as_millis()
     }
 
-    /// Appends source to sink.
-    pub fn append_to_sink(&self, path: PathBuf, vol: i16) -> Result<(), AudioError> {
-        let file = File::open(path).map_err(AudioError::Io)?;
-        let reader = BufReader::new(file);
-        let source = Decoder::new(reader).map_err(AudioError::Decoder)?;
+    /// Appends source (or the range of it) to sink, returning whether there was a playing sink to append to.
+    /// Until it starts playing, the appended track can be swapped with `replace_next`.
+    pub fn append_to_sink(
+        &self,
+        path: PathBuf,
+        range: Option<CueRange>,
+        vol: i16,
+    ) -> Result<bool, AudioError> {
+        let source = self.track_source(path, range)?;
 
-        {
+        let appended = {
             let sink_guard = self.sink.lock().expect("Mutex poisoned");
-            if let Some(ref sink) = *sink_guard {
-                if 1 <= sink.len() {
-                    sink.append(source);
+            match *sink_guard {
+                Some(ref sink) if 1 <= sink.len() => {
+                    let (track, handle) = NextTrack::new(source);
+                    sink.append(track);
+                    *self.next.lock().expect("Mutex poisoned") = Some(handle);
+                    true
                 }
+                _ => false,
             }
-        }
+        };
 
         self.set_volume(vol);
-        Ok(())
+        Ok(appended)
+    }
+
+    /// Swaps the appended track that hasn't started yet for `source` (or for nothing), leaving the current one playing.
+    /// Returns false if no appended track is waiting.
+    pub fn replace_next(&self, source: Option<TrackSource>) -> bool {
+        match &*self.next.lock().expect("Mutex poisoned") {
+            Some(handle) => handle.swap(source),
+            None => false,
+        }
+    }
+
+    /// Seeks the playing source to the given position.
+    pub fn seek(&self, pos: Duration) -> Result<(), AudioError> {
+        let sink_guard = self.sink.lock().expect("Mutex poisoned");
+        match &*sink_guard {
+            Some(sink) => sink.try_seek(pos).map_err(AudioError::Seek),
+            None => Ok(()),
+        }
     }
 
     /// Removes all currently loaded Sources from the Sink, and pauses it.
@@ -115,6 +259,8 @@ impl SinkHandler {
         if let Some(ref sink) = *sink_guard {
             sink.clear();
         }
+        *self.next.lock().expect("Mutex poisoned") = None;
+        self.skips.clear();
     }
 
     /// Returns how many elements are in the sink.
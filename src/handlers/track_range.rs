@@ -0,0 +1,115 @@
+use crate::data::metadata::cue::CueRange;
+use rodio::{Sample, Source, source::SeekError};
+use std::time::Duration;
+
+/// Wraps a source so it only plays a range of it, e.g. one CUE track of a whole-album file.
+/// Positions and seeks are relative to the start of the range.
+pub struct TrackRange<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    input: S,
+    start: Duration,
+    end: Option<Duration>,
+    remaining: Option<u64>,
+}
+
+impl<S> TrackRange<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    /// Moves the input to the start of the range; without a range the input plays whole.
+    pub fn new(input: S, range: Option<CueRange>) -> Self {
+        let start = Duration::from_secs_f64(range.map(|range| range.start).unwrap_or(0.0));
+        let end = range
+            .and_then(|range| range.end)
+            .map(Duration::from_secs_f64);
+        let mut track = Self {
+            input,
+            start,
+            end,
+            remaining: None,
+        };
+        if !start.is_zero() && track.input.try_seek(start).is_err() {
+            // Formats that can't seek get there by decoding.
+            let skip = track.samples_in(start);
+            for _ in (&mut track.input).take(skip as usize) {}
+        }
+        track.remaining = end.map(|end| track.samples_in(end.saturating_sub(start)));
+        track
+    }
+
+    /// Returns how many samples (over all channels) make up `duration`.
+    fn samples_in(&self, duration: Duration) -> u64 {
+        (duration.as_secs_f64() * self.input.sample_rate() as f64) as u64
+            * self.input.channels() as u64
+    }
+}
+
+impl<S> Iterator for TrackRange<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        match &mut self.remaining {
+            Some(0) => None,
+            Some(remaining) => {
+                *remaining -= 1;
+                self.input.next()
+            }
+            None => self.input.next(),
+        }
+    }
+}
+
+impl<S> Source for TrackRange<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.remaining {
+            Some(remaining) => Some(
+                self.input
+                    .current_frame_len()
+                    .map_or(remaining as usize, |len| len.min(remaining as usize)),
+            ),
+            None => self.input.current_frame_len(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self.end {
+            Some(end) => Some(end.saturating_sub(self.start)),
+            None => self
+                .input
+                .total_duration()
+                .map(|total| total.saturating_sub(self.start)),
+        }
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let pos = match self.end {
+            Some(end) => pos.min(end.saturating_sub(self.start)),
+            None => pos,
+        };
+        self.input.try_seek(self.start + pos)?;
+        self.remaining = self
+            .end
+            .map(|end| self.samples_in(end.saturating_sub(self.start + pos)));
+        Ok(())
+    }
+}
@@ -11,10 +11,13 @@ mod tui;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = ratatui::init();
-    let mut app = App::new(env::current_dir()?)?;
-    let run = app.run(&mut terminal)?;
+    let run = env::current_dir()
+        .map_err(Box::from)
+        .and_then(App::new)
+        .and_then(|mut app| app.run(&mut terminal));
+    // The terminal is restored before any error is reported, so it isn't left in raw mode.
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
     terminal.show_cursor()?;
-    Ok(run)
+    run
 }
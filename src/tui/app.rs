@@ -1,27 +1,45 @@
 use crate::{
     data::{
         config::{ConfigData, load_config},
-        metadata::file_metadata::FileMetadata,
-        queue::metadata_queue::MetadataQueue,
+        library::scan::LibraryScanner,
+        metadata::{
+            cache::compact_metadata_cache,
+            file_metadata::{FileMetadata, TrackId},
+            stream_info::StreamInfo,
+        },
+        playlist::{repair::RepairSearch, saved::SavedPlaylists, smart::SmartRun},
+        queue::{
+            history::History,
+            named_queue::{NamedQueue, load_queues},
+            play_queue::PlayQueue,
+            undo::UndoHistory,
+        },
+        session::load_session,
     },
     handlers::input_handler::InputHandler,
-    tui::render::fs_browser::FileBrowser,
+    tui::{popup::Popup, render::fs_browser::FileBrowser},
 };
 use crossterm::event::{self, Event};
 use ratatui::DefaultTerminal;
+use serde::{Deserialize, Serialize};
 use std::{
     error::Error,
     path::PathBuf,
+    sync::mpsc::Receiver,
     time::{Duration, Instant},
 };
 
 pub const PLAYABLE: [&str; 5] = ["flac", "m4v", "mp3", "mp4", "wav"];
+pub const PLAYLISTS: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
 const TICK: Duration = Duration::from_millis(100);
 
 /// Current tab information.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
 pub enum Tab {
     Playlist,
     Browser,
+    Saved,
 }
 
 /// App state.
@@ -33,14 +51,40 @@ pub enum State {
 
 /// The main application.
 pub struct App {
+    pub active_queue: usize,
     pub audio: InputHandler,
+    pub auto_dj: bool,
+    /// The running pick of auto-DJ's next track.
+    pub auto_dj_pick: Option<Receiver<Option<FileMetadata>>>,
+    /// The running read of the playing file's format badge.
+    pub badge_probe: Option<Receiver<(PathBuf, String)>>,
     pub config: ConfigData,
-    pub data: FileMetadata,
     pub file_browser: FileBrowser,
-    pub meta_manager: MetadataQueue,
-    pub path_queue: Vec<PathBuf>,
+    /// The playing file and its format badge, read again only when the file changes.
+    pub format_badge: Option<(PathBuf, String)>,
+    /// The running read of the tracks a playlist is generated from.
+    pub generator_pool: Option<Receiver<Vec<FileMetadata>>>,
+    pub history: History,
+    /// The running read of the inspected file.
+    pub inspecting: Option<Receiver<Option<StreamInfo>>>,
+    pub library: Option<Vec<PathBuf>>,
+    pub library_scanner: LibraryScanner,
+    /// A message shown over the status line for a few seconds, e.g. an error.
+    pub message: Option<(String, Instant)>,
+    pub popup: Option<Popup>,
+    pub preloaded: Option<TrackId>,
+    pub queue: PlayQueue,
+    pub queues: Vec<NamedQueue>,
+    /// The running search for replacements of a playlist's missing entries.
+    pub repair_search: Option<Receiver<RepairSearch>>,
+    pub saved_playlists: SavedPlaylists,
+    pub show_history: bool,
+    pub show_remaining: bool,
+    /// The running gathering of smart playlist tracks.
+    pub smart_run: Option<Receiver<SmartRun>>,
     pub state: State,
     pub tab: Tab,
+    pub undo: UndoHistory,
 }
 
 impl App {
@@ -52,28 +96,79 @@ impl App {
             })
             .unwrap_or(initial_dir);
 
-        Ok(Self {
-            config: load_config(),
-            meta_manager: MetadataQueue::new(),
+        let config = load_config();
+        let (queues, active_queue) = load_queues(config.playback.undo_depth);
+
+        let mut app = Self {
+            active_queue,
+            audio: InputHandler::new(config.playback.clone())?,
+            auto_dj: config.auto_dj.enabled,
+            auto_dj_pick: None,
+            badge_probe: None,
             file_browser: FileBrowser::new(final_dir),
-            audio: InputHandler::new()?,
-            data: FileMetadata::new(),
-            path_queue: Vec::new(),
+            format_badge: None,
+            generator_pool: None,
+            history: History::new(config.playback.history_size),
+            inspecting: None,
+            library: None,
+            library_scanner: LibraryScanner::new(),
+            message: None,
+            undo: UndoHistory::new(config.playback.undo_depth),
+            popup: None,
+            preloaded: None,
+            queue: PlayQueue::new(),
+            queues,
+            repair_search: None,
+            saved_playlists: SavedPlaylists::new(),
+            show_history: false,
+            show_remaining: false,
+            smart_run: None,
+            config,
             tab: Tab::Browser,
             state: State::Running,
-        })
+        };
+        if let Some(session) = load_session() {
+            app.restore_session(session);
+        }
+        // The saved active queue comes back where it was left, paused unless configured otherwise.
+        app.unpark_queue();
+        app.resume_queue(!app.config.playback.resume_playing);
+        Ok(app)
     }
 
     /// Renders the tui.
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Box<dyn Error>> {
         self.file_browser.update_entries()?;
         let mut last_tick = Instant::now();
+        let mut last_save = Instant::now();
+        let autosave = Duration::from_secs(self.config.playback.autosave_secs);
 
         while self.state == State::Running {
+            self.sync_playback();
+            self.top_up_auto_dj();
+            self.queue.sync_selection();
+            self.history.sync_selection();
+            self.file_browser.poll_metadata();
+            self.poll_library();
+            self.poll_auto_dj();
+            self.poll_repair_search();
+            self.poll_smart_run();
+            self.poll_generator_pool();
+            self.saved_playlists.poll_loaded();
+            self.update_format_badge();
+            self.poll_inspector();
             terminal.draw(|frame| self.render(frame))?;
             let timeout = TICK.saturating_sub(last_tick.elapsed());
             if !event::poll(timeout)? {
+                self.check_preview();
                 last_tick = Instant::now();
+                // Saves now and then so a crash loses little.
+                if !autosave.is_zero() && autosave <= last_save.elapsed() {
+                    if let Err(e) = self.save_session() {
+                        self.show_message(format!("Failed to save session: {}", e));
+                    }
+                    last_save = Instant::now();
+                }
                 continue;
             }
             if let Event::Key(key) = event::read()? {
@@ -81,6 +176,7 @@ impl App {
                 self.file_browser.update_entries()?;
             }
         }
-        Ok(())
+        self.save_session()?;
+        compact_metadata_cache().map_err(Box::from)
     }
 }
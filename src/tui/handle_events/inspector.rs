@@ -0,0 +1,108 @@
+use crate::{
+    data::metadata::{file_metadata::FileMetadata, stream_info::StreamInfo},
+    tui::{
+        app::{App, Tab},
+        popup::Popup,
+    },
+};
+use std::{
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+
+impl App {
+    /// Inspects the highlighted browser file or playlist entry.
+    pub fn handle_inspect_selected(&mut self) {
+        let track = match self.tab {
+            Tab::Browser => self.file_browser.track_at(self.file_browser.selected),
+            Tab::Playlist => self.queue.items.get(self.queue.selected).cloned(),
+            Tab::Saved => None,
+        };
+        if let Some(track) = track {
+            self.open_inspector(track);
+        }
+    }
+
+    /// Inspects the playing track.
+    pub fn handle_inspect_playing(&mut self) {
+        if let Some(track) = self.queue.current().cloned() {
+            self.open_inspector(track);
+        }
+    }
+
+    /// Opens the inspector right away and reads the file in the background.
+    fn open_inspector(&mut self, track: FileMetadata) {
+        let (sender, receiver) = mpsc::channel();
+        let path = track.file_path.clone();
+        thread::spawn(move || {
+            let _ = sender.send(StreamInfo::read(&path));
+        });
+        self.inspecting = Some(receiver);
+        self.popup = Some(Popup::Inspector {
+            info: None,
+            reading: true,
+            scroll: 0,
+            track: Box::new(track),
+        });
+    }
+
+    /// Fills in the inspector once its file was read; the result is dropped if it was closed.
+    pub fn poll_inspector(&mut self) {
+        let Some(receiver) = &self.inspecting else {
+            return;
+        };
+        // A reader that died couldn't read the file either.
+        let read = match receiver.try_recv() {
+            Ok(info) => info,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => None,
+        };
+        self.inspecting = None;
+        if let Some(Popup::Inspector { info, reading, .. }) = &mut self.popup
+            && *reading
+        {
+            *info = read;
+            *reading = false;
+        }
+    }
+
+    /// Reads the playing file's format badge in the background when the file changes.
+    pub fn update_format_badge(&mut self) {
+        if let Some(receiver) = &self.badge_probe
+            && let Ok((path, badge)) = receiver.try_recv()
+        {
+            self.badge_probe = None;
+            if self
+                .format_badge
+                .as_ref()
+                .is_some_and(|(current, _)| *current == path)
+            {
+                self.format_badge = Some((path, badge));
+            }
+        }
+
+        let Some(current) = self.queue.current() else {
+            self.format_badge = None;
+            return;
+        };
+        if self
+            .format_badge
+            .as_ref()
+            .is_some_and(|(path, _)| *path == current.file_path)
+        {
+            return;
+        }
+
+        // No badge is shown until the file was read.
+        let path = current.file_path.clone();
+        self.format_badge = Some((path.clone(), String::new()));
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let badge = StreamInfo::read_badge(&path)
+                .map(|info| info.badge())
+                .unwrap_or_default();
+            let _ = sender.send((path, badge));
+        });
+        self.badge_probe = Some(receiver);
+    }
+}
@@ -1,20 +1,77 @@
 use crate::{
-    data::metadata::file_metadata::FileMetadata,
+    data::queue::{play_queue::EnqueueMode, playback_modes::PlaybackModes},
     tui::app::{App, State, Tab},
 };
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 impl App {
     /// Handles key events.
     pub fn handle_key_event(&mut self, key_event: KeyEvent) {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
         let vol_delta = self.config.controls.vol_delta;
 
+        if self.popup.is_some() {
+            self.handle_popup_key_event(key_event);
+            return;
+        }
+
+        // Anything that moves the cursor or changes playback ends a running preview.
+        if !matches!(
+            key_event.code,
+            KeyCode::Char('v')
+                | KeyCode::Char('=')
+                | KeyCode::Char('+')
+                | KeyCode::Char('-')
+                | KeyCode::Char('_')
+        ) {
+            self.audio.stop_preview();
+        }
+
         match key_event.code {
             KeyCode::Char('q') => self.state = State::Quit,
 
+            KeyCode::Char('s') | KeyCode::Char('>') => self.handle_skip(),
+            KeyCode::Char('<') => self.handle_previous(),
+            KeyCode::Char('c') => self.handle_clear(),
+
+            KeyCode::Char('=') | KeyCode::Char('+') => self.audio.adjust_volume(vol_delta),
+            KeyCode::Char('-') | KeyCode::Char('_') => self.audio.adjust_volume(-vol_delta),
+            KeyCode::Char('p') => self.audio.toggle_play_pause(),
+            KeyCode::Char('t') => self.show_remaining = !self.show_remaining,
+
+            KeyCode::Char('u') => self.handle_undo(),
+            KeyCode::Char('r') if ctrl => self.handle_redo(),
+
+            KeyCode::Char('r') => self.handle_mode_change(PlaybackModes::cycle_repeat),
+            KeyCode::Char('z') => self.handle_mode_change(PlaybackModes::cycle_random),
+            KeyCode::Char('R') => self.handle_mode_change(PlaybackModes::toggle_consume),
+            KeyCode::Char('A') => self.handle_auto_dj_toggle(),
+            KeyCode::Char('I') => self.handle_inspect_playing(),
+
+            KeyCode::Char('1') => self.tab = Tab::Browser,
+            KeyCode::Char('2') => self.tab = Tab::Playlist,
+            KeyCode::Char('3') => self.show_saved_playlists(),
+            KeyCode::Char('[') => self.handle_previous_queue(),
+            KeyCode::Char(']') => self.handle_next_queue(),
+
+            _ => match self.tab {
+                Tab::Browser => self.handle_browser_key_event(key_event),
+                Tab::Playlist if self.show_history => self.handle_history_key_event(key_event),
+                Tab::Playlist => self.handle_playlist_key_event(key_event),
+                Tab::Saved => self.handle_saved_key_event(key_event),
+            },
+        }
+    }
+
+    /// Handles key events specific to the file browser.
+    fn handle_browser_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
             KeyCode::Enter => self.handle_play(),
             KeyCode::Char('a') => self.handle_append(),
-            KeyCode::Char('s') => self.handle_skip(),
+            KeyCode::Char('n') => self.handle_play_next(),
+            KeyCode::Char('v') => self.handle_preview(),
+            KeyCode::Char('G') => self.handle_generate(),
+            KeyCode::Char('i') => self.handle_inspect_selected(),
 
             KeyCode::Up | KeyCode::Char('k') => self.file_browser.navigate_up(),
             KeyCode::Down | KeyCode::Char('j') => self.file_browser.navigate_down(),
@@ -26,18 +83,78 @@ impl App {
 
             KeyCode::Char('g') => self.file_browser.goto_music_dir(),
 
-            KeyCode::Char('c') => {
-                self.audio.clear_sink();
-                self.path_queue.clear();
-                self.data = FileMetadata::new();
-            }
+            _ => {}
+        }
+    }
 
-            KeyCode::Char('=') | KeyCode::Char('+') => self.audio.adjust_volume(vol_delta),
-            KeyCode::Char('-') | KeyCode::Char('_') => self.audio.adjust_volume(vol_delta * -1),
-            KeyCode::Char('p') => self.audio.toggle_play_pause(),
+    /// Handles key events specific to the playlist.
+    fn handle_playlist_key_event(&mut self, key_event: KeyEvent) {
+        let shift = key_event.modifiers.contains(KeyModifiers::SHIFT);
 
-            KeyCode::Char('1') => self.tab = Tab::Browser,
-            KeyCode::Char('2') => self.tab = Tab::Playlist,
+        match key_event.code {
+            KeyCode::Char('H') => self.show_history = true,
+
+            KeyCode::Enter => self.handle_jump_play(),
+            KeyCode::Char('d') | KeyCode::Delete => self.handle_remove(),
+            KeyCode::Char('Z') => self.handle_shuffle(),
+
+            KeyCode::Char('N') => self.handle_new_queue(),
+            KeyCode::Char('E') => self.handle_rename_queue(),
+            KeyCode::Char('X') => self.handle_delete_queue(),
+            KeyCode::Char('S') => self.handle_save_playlist(),
+            KeyCode::Char('i') => self.handle_inspect_selected(),
+
+            KeyCode::Up if shift => self.handle_move_up(),
+            KeyCode::Down if shift => self.handle_move_down(),
+            KeyCode::Char('K') => self.handle_move_up(),
+            KeyCode::Char('J') => self.handle_move_down(),
+
+            KeyCode::Up | KeyCode::Char('k') => self.queue.navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.queue.navigate_down(),
+
+            KeyCode::PageUp => self.queue.goto_top(),
+            KeyCode::PageDown => self.queue.goto_bottom(),
+
+            _ => {}
+        }
+    }
+
+    /// Handles key events specific to the saved playlists.
+    fn handle_saved_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Enter => self.handle_open_saved(EnqueueMode::Play),
+            KeyCode::Char('a') => self.handle_open_saved(EnqueueMode::Append),
+            KeyCode::Char('n') => self.handle_open_saved(EnqueueMode::Next),
+
+            KeyCode::Char('E') => self.handle_rename_saved(),
+            KeyCode::Char('D') => self.handle_duplicate_saved(),
+            KeyCode::Char('X') | KeyCode::Delete => self.handle_delete_saved(),
+            KeyCode::Char(' ') => self.saved_playlists.toggle_mark(),
+            KeyCode::Char('M') => self.handle_merge_saved(),
+            KeyCode::Char('g') => self.refresh_saved_playlists(),
+
+            KeyCode::Up | KeyCode::Char('k') => self.saved_playlists.navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.saved_playlists.navigate_down(),
+
+            KeyCode::PageUp => self.saved_playlists.goto_top(),
+            KeyCode::PageDown => self.saved_playlists.goto_bottom(),
+
+            _ => {}
+        }
+    }
+
+    /// Handles key events specific to the play history.
+    fn handle_history_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Char('H') | KeyCode::Esc => self.show_history = false,
+
+            KeyCode::Enter => self.handle_history_replay(),
+
+            KeyCode::Up | KeyCode::Char('k') => self.history.navigate_up(),
+            KeyCode::Down | KeyCode::Char('j') => self.history.navigate_down(),
+
+            KeyCode::PageUp => self.history.goto_top(),
+            KeyCode::PageDown => self.history.goto_bottom(),
 
             _ => {}
         }
@@ -1 +1,4 @@
+pub mod inspector;
 pub mod key_event;
+pub mod popup_event;
+pub mod preview;
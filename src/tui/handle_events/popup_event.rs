@@ -0,0 +1,205 @@
+use crate::{
+    data::{
+        library::generator::{generate, parse_generator_input},
+        playlist::saved::target_path,
+        queue::play_queue::EnqueueMode,
+    },
+    tui::{
+        app::App,
+        popup::{InputAction, PendingAction, Popup},
+    },
+};
+use crossterm::event::{KeyCode, KeyEvent};
+
+impl App {
+    /// Handles key events while a popup is open.
+    pub fn handle_popup_key_event(&mut self, key_event: KeyEvent) {
+        let Some(popup) = self.popup.take() else {
+            return;
+        };
+
+        match popup {
+            Popup::Confirm { message, action } => match key_event.code {
+                KeyCode::Char('y') | KeyCode::Enter => self.run_pending_action(action),
+                KeyCode::Char('n') | KeyCode::Esc => {}
+                _ => self.popup = Some(Popup::Confirm { message, action }),
+            },
+            Popup::Input {
+                prompt,
+                mut input,
+                action,
+            } => match key_event.code {
+                KeyCode::Enter if self.accepts_input(&input, &action) => {
+                    self.run_input_action(input.trim().to_string(), action)
+                }
+                KeyCode::Esc => {}
+                code => {
+                    match code {
+                        KeyCode::Char(c) => input.push(c),
+                        KeyCode::Backspace => {
+                            input.pop();
+                        }
+                        _ => {}
+                    }
+                    self.popup = Some(Popup::Input {
+                        prompt,
+                        input,
+                        action,
+                    });
+                }
+            },
+            Popup::Generated {
+                pool,
+                reading,
+                target_secs,
+                tracks,
+            } => match key_event.code {
+                KeyCode::Char('r') | KeyCode::Enter if !tracks.is_empty() => {
+                    self.replace_queue(tracks)
+                }
+                KeyCode::Char('a') if !tracks.is_empty() => {
+                    self.enqueue(tracks, EnqueueMode::Append)
+                }
+                KeyCode::Char('g') if !reading => {
+                    let tracks = generate(&pool, target_secs, &self.config.generator);
+                    self.popup = Some(Popup::Generated {
+                        pool,
+                        reading,
+                        target_secs,
+                        tracks,
+                    });
+                }
+                KeyCode::Esc => {}
+                _ => {
+                    self.popup = Some(Popup::Generated {
+                        pool,
+                        reading,
+                        target_secs,
+                        tracks,
+                    })
+                }
+            },
+            Popup::Repair {
+                items,
+                mode,
+                playlist,
+                mut repairs,
+                searching,
+                mut selected,
+            } => match key_event.code {
+                KeyCode::Enter | KeyCode::Char('y') if !searching => {
+                    self.apply_repairs(&playlist, items, repairs, mode)
+                }
+                KeyCode::Char('i') => self.queue_playlist_items(items, mode),
+                KeyCode::Esc => {}
+                code => {
+                    let repair = repairs
+                        .get_mut(selected)
+                        .filter(|repair| !repair.candidates.is_empty());
+                    match (code, repair) {
+                        (KeyCode::Up | KeyCode::Char('k'), _) => {
+                            selected = selected.saturating_sub(1)
+                        }
+                        (KeyCode::Down | KeyCode::Char('j'), _) => {
+                            selected = (selected + 1).min(repairs.len().saturating_sub(1))
+                        }
+                        (KeyCode::Char(' '), Some(repair)) => repair.accepted = !repair.accepted,
+                        (KeyCode::Left | KeyCode::Char('h'), Some(repair)) => {
+                            repair.chosen = repair
+                                .chosen
+                                .checked_sub(1)
+                                .unwrap_or(repair.candidates.len() - 1)
+                        }
+                        (KeyCode::Right | KeyCode::Char('l'), Some(repair)) => {
+                            repair.chosen = (repair.chosen + 1) % repair.candidates.len()
+                        }
+                        _ => {}
+                    }
+                    self.popup = Some(Popup::Repair {
+                        items,
+                        mode,
+                        playlist,
+                        repairs,
+                        searching,
+                        selected,
+                    });
+                }
+            },
+            Popup::Inspector {
+                info,
+                reading,
+                mut scroll,
+                track,
+            } => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('i') | KeyCode::Char('I') => {}
+                code => {
+                    // The last line stays in view however far down it is scrolled.
+                    let last = self
+                        .inspector_lines(info.as_ref(), reading, &track)
+                        .len()
+                        .saturating_sub(1) as u16;
+                    match code {
+                        KeyCode::Up | KeyCode::Char('k') => scroll = scroll.saturating_sub(1),
+                        KeyCode::Down | KeyCode::Char('j') => scroll = (scroll + 1).min(last),
+                        KeyCode::PageUp => scroll = 0,
+                        _ => {}
+                    }
+                    self.popup = Some(Popup::Inspector {
+                        info,
+                        reading,
+                        scroll,
+                        track,
+                    });
+                }
+            },
+        }
+    }
+
+    /// Returns true if the input can be submitted; queue names must be non-empty and unique.
+    fn accepts_input(&self, input: &str, action: &InputAction) -> bool {
+        let input = input.trim();
+        match action {
+            InputAction::NewQueue | InputAction::RenameQueue => {
+                !input.is_empty() && !self.queue_name_taken(input)
+            }
+            InputAction::Generate(_) => parse_generator_input(input).is_some(),
+            InputAction::SavePlaylist => !input.is_empty() && !input.contains('/'),
+            // Renaming, copying and merging never overwrite a playlist.
+            InputAction::RenamePlaylist(path) | InputAction::DuplicatePlaylist(path) => {
+                !input.is_empty() && !input.contains('/') && !target_path(path, input).exists()
+            }
+            InputAction::MergePlaylists(_) => {
+                !input.is_empty()
+                    && !input.contains('/')
+                    && !self.saved_playlist_exists(input, "m3u8")
+            }
+        }
+    }
+
+    /// Runs an action with the text the user submitted.
+    fn run_input_action(&mut self, input: String, action: InputAction) {
+        match action {
+            InputAction::NewQueue => self.create_queue(input),
+            InputAction::RenameQueue => self.rename_queue(input),
+            InputAction::SavePlaylist => self.save_playlist_as(&input),
+            InputAction::RenamePlaylist(path) => self.rename_saved(&path, &input),
+            InputAction::DuplicatePlaylist(path) => self.duplicate_saved(&path, &input),
+            InputAction::MergePlaylists(sources) => self.merge_saved(&sources, &input),
+            InputAction::Generate(dir) => {
+                if let Some((target_secs, genre)) = parse_generator_input(&input) {
+                    self.start_generator(&dir, target_secs, genre);
+                }
+            }
+        }
+    }
+
+    /// Runs an action the user confirmed.
+    fn run_pending_action(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::Enqueue(files, mode) => self.enqueue_files(files, mode),
+            PendingAction::DeleteQueue => self.delete_queue(),
+            PendingAction::DeletePlaylist(path) => self.delete_saved(&path),
+            PendingAction::SavePlaylist(path) => self.write_playlist(path),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::{data::metadata::file_metadata::FileMetadata, tui::app::App};
+
+impl App {
+    /// Previews the highlighted browser file without touching the queue.
+    pub fn handle_preview(&mut self) {
+        if let Some(path) = self.file_browser.entries.get(self.file_browser.selected)
+            && !path.is_dir()
+        {
+            let duration_hint = FileMetadata::get_file_data(path).duration_as_secs;
+            self.audio
+                .start_preview(path, &self.config.preview, duration_hint);
+        }
+    }
+
+    /// Stops the preview once it has played for its configured length.
+    pub fn check_preview(&mut self) {
+        if self.audio.preview_finished() {
+            self.audio.stop_preview();
+        }
+    }
+}
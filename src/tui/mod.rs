@@ -1,3 +1,4 @@
 pub mod app;
 pub mod handle_events;
+pub mod popup;
 pub mod render;
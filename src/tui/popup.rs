@@ -0,0 +1,61 @@
+use crate::data::{
+    metadata::{file_metadata::FileMetadata, stream_info::StreamInfo},
+    playlist::repair::Repair,
+    queue::play_queue::EnqueueMode,
+};
+use std::path::PathBuf;
+
+/// An action that runs once the user confirms it.
+pub enum PendingAction {
+    Enqueue(Vec<PathBuf>, EnqueueMode),
+    DeleteQueue,
+    DeletePlaylist(PathBuf),
+    SavePlaylist(PathBuf),
+}
+
+/// What a submitted text input is used for.
+pub enum InputAction {
+    NewQueue,
+    RenameQueue,
+    Generate(PathBuf),
+    SavePlaylist,
+    RenamePlaylist(PathBuf),
+    DuplicatePlaylist(PathBuf),
+    MergePlaylists(Vec<PathBuf>),
+}
+
+/// A dialog drawn over the current tab; it takes all key input while open.
+pub enum Popup {
+    Confirm {
+        message: String,
+        action: PendingAction,
+    },
+    Input {
+        prompt: String,
+        input: String,
+        action: InputAction,
+    },
+    /// A generated playlist waiting to replace or be appended to the queue; empty while `reading` the pool.
+    Generated {
+        pool: Vec<FileMetadata>,
+        reading: bool,
+        target_secs: f64,
+        tracks: Vec<FileMetadata>,
+    },
+    /// Replacements for a playlist's missing entries, waiting for review; empty while `searching`.
+    Repair {
+        items: Vec<FileMetadata>,
+        mode: EnqueueMode,
+        playlist: PathBuf,
+        repairs: Vec<Repair>,
+        searching: bool,
+        selected: usize,
+    },
+    /// A track's stream parameters and raw tags; `info` is None while `reading` and if the decoder can't read the file.
+    Inspector {
+        info: Option<StreamInfo>,
+        reading: bool,
+        scroll: u16,
+        track: Box<FileMetadata>,
+    },
+}
@@ -1,16 +1,35 @@
-use crate::tui::app::{App, Tab};
+use crate::{
+    data::{
+        library::generator::duration_of,
+        metadata::{file_metadata::FileMetadata, stream_info::StreamInfo},
+    },
+    tui::{
+        app::{App, Tab},
+        popup::Popup,
+    },
+};
+use chrono::Local;
 use ratatui::{
     Frame,
-    layout::{Alignment, Constraint, Layout, Margin},
+    layout::{Alignment, Constraint, Flex, Layout, Margin},
     style::{Color, Style},
     symbols::scrollbar,
     text::{Line, Span},
     widgets::{
-        Block, BorderType, Borders, List, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
-        ScrollbarState,
+        Block, BorderType, Borders, Clear, List, Padding, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
     },
 };
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// How many generated tracks the generator popup lists.
+const GENERATED_PREVIEW_LINES: usize = 15;
+/// How long a message stays over the status line.
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
 
 impl App {
     /// Shortens the code necessary to set the color of a terminal element.
@@ -18,8 +37,50 @@ impl App {
         Color::from_str(color).unwrap_or(Color::Reset)
     }
 
+    /// Returns the current queue item's metadata, or empty metadata if nothing is queued.
+    pub fn current_data(&self) -> FileMetadata {
+        self.queue
+            .current()
+            .cloned()
+            .unwrap_or_else(FileMetadata::new)
+    }
+
+    /// Describes the queue's total length, the time left and when it will finish.
+    /// Totals that skip items of unknown length are marked with a '+'.
+    pub fn queue_time_summary(&self) -> String {
+        let mark = |unknown: usize| if 0 < unknown { "+" } else { "" };
+        let (total, total_unknown) = self.queue.total_duration();
+        let mut summary = format!(
+            ", {}{} total",
+            FileMetadata::format_secs(total),
+            mark(total_unknown)
+        );
+
+        if self.queue.current.is_some() {
+            let position = self.audio.sink_pos_millis() as f64 / 1000.0;
+            let (left, left_unknown) = self.queue.remaining_duration(position);
+            let finish = Local::now() + chrono::Duration::seconds(left as i64);
+            summary.push_str(&format!(
+                ", {}{} left, ends {}{}",
+                FileMetadata::format_secs(left),
+                mark(left_unknown),
+                finish.format("%H:%M"),
+                mark(left_unknown)
+            ));
+        }
+        if 0 < total_unknown {
+            summary.push_str(&format!(", {} unknown", total_unknown));
+        }
+        summary
+    }
+
+    /// Shows a message over the status line for a few seconds.
+    pub fn show_message(&mut self, message: String) {
+        self.message = Some((message, Instant::now()));
+    }
+
     /// Turns '/home/USER' into '~' when displaying a path.
-    pub fn format_display_path(&self, path: &PathBuf) -> String {
+    pub fn format_display_path(&self, path: &Path) -> String {
         let current_dir = path.to_string_lossy().to_string();
         if let Some(home) = dirs::home_dir() {
             let home_str = home.to_string_lossy();
@@ -81,50 +142,143 @@ impl App {
         frame.render_widget(self.progress_bar(), bottom);
 
         match self.tab {
-            Tab::Playlist => {
+            Tab::Playlist if self.show_history => {
                 // STATUS
-                match self.audio.get_len() {
-                    0 => frame.render_widget(
-                        Paragraph::new(Line::from(vec![Span::styled(
-                            "playlist is empty",
-                            Style::default().fg(self.get_color(status)),
-                        )]))
-                        .block(Block::new())
-                        .alignment(Alignment::Center),
-                        info,
-                    ),
-                    1 => frame.render_widget(
-                        Paragraph::new(Line::from(vec![Span::styled(
-                            "playlist (1 item)",
-                            Style::default().fg(self.get_color(status)),
-                        )]))
-                        .block(Block::new())
-                        .alignment(Alignment::Center),
-                        info,
-                    ),
-                    _ => frame.render_widget(
-                        Paragraph::new(Line::from(vec![Span::styled(
-                            format!("playlist ({} items)", self.audio.get_len()),
-                            Style::default().fg(self.get_color(status)),
-                        )]))
-                        .block(Block::new())
-                        .alignment(Alignment::Center),
-                        info,
-                    ),
-                }
-                // MIDDLE
                 frame.render_widget(
-                    List::new(self.meta_manager.list_items())
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        format!("history (last {} played)", self.history.items.len()),
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                frame.render_stateful_widget(
+                    List::new(self.history.list_items())
                         .block(middle_block)
                         .highlight_style(Style::default().fg(self.get_color(highlight_color))),
                     middle,
+                    &mut self.history.list_state.clone(),
+                );
+                frame.render_stateful_widget(
+                    Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                        .symbols(scrollbar::VERTICAL)
+                        .begin_symbol(None)
+                        .end_symbol(None)
+                        .track_symbol(None),
+                    middle.inner(Margin {
+                        horizontal: 0,
+                        vertical: 1,
+                    }),
+                    &mut ScrollbarState::new(self.history.items.len())
+                        .position(self.history.selected),
+                );
+            }
+            Tab::Playlist => {
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match self.queue.len() {
+                            0 => "playlist is empty".to_string(),
+                            1 => format!("playlist (1 item{})", self.queue_time_summary()),
+                            len => format!("playlist ({} items{})", len, self.queue_time_summary()),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                frame.render_stateful_widget(
+                    List::new(
+                        self.queue
+                            .list_items(&self.config.playlist.columns, &self.config.colors),
+                    )
+                    .block(middle_block)
+                    .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                    middle,
+                    &mut self.queue.list_state.clone(),
+                );
+                frame.render_stateful_widget(
+                    Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                        .symbols(scrollbar::VERTICAL)
+                        .begin_symbol(None)
+                        .end_symbol(None)
+                        .track_symbol(None),
+                    middle.inner(Margin {
+                        horizontal: 0,
+                        vertical: 1,
+                    }),
+                    &mut ScrollbarState::new(self.queue.len()).position(self.queue.selected),
+                );
+            }
+            Tab::Saved => {
+                // STATUS
+                frame.render_widget(
+                    Paragraph::new(Line::from(vec![Span::styled(
+                        match self.saved_playlists.playlists.len() {
+                            0 => format!(
+                                "no saved playlists in {}",
+                                self.format_display_path(
+                                    &self.config.directories.playlist_directory
+                                )
+                            ),
+                            len => format!(
+                                "saved playlists ({} in {})",
+                                len,
+                                self.format_display_path(
+                                    &self.config.directories.playlist_directory
+                                )
+                            ),
+                        },
+                        Style::default().fg(self.get_color(status)),
+                    )]))
+                    .block(Block::new())
+                    .alignment(Alignment::Center),
+                    info,
+                );
+                // MIDDLE
+                let [list, preview] =
+                    Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                        .areas(middle);
+                let preview_title = self
+                    .saved_playlists
+                    .selected_playlist()
+                    .map(|playlist| format!(" {} ", playlist.name()))
+                    .unwrap_or_default();
+                frame.render_stateful_widget(
+                    List::new(self.saved_playlists.list_items())
+                        .block(middle_block.clone())
+                        .highlight_style(Style::default().fg(self.get_color(highlight_color))),
+                    list,
+                    &mut self.saved_playlists.list_state.clone(),
+                );
+                frame.render_widget(
+                    List::new(self.saved_playlists.preview_items())
+                        .block(middle_block.title(preview_title)),
+                    preview,
+                );
+                frame.render_stateful_widget(
+                    Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                        .symbols(scrollbar::VERTICAL)
+                        .begin_symbol(None)
+                        .end_symbol(None)
+                        .track_symbol(None),
+                    list.inner(Margin {
+                        horizontal: 0,
+                        vertical: 1,
+                    }),
+                    &mut ScrollbarState::new(self.saved_playlists.playlists.len())
+                        .position(self.saved_playlists.selected),
                 );
             }
             Tab::Browser => {
                 // STATUS
                 frame.render_widget(
                     Paragraph::new(Line::from(vec![Span::styled(
-                        format!("{}", display_path),
+                        display_path,
                         Style::default().fg(self.get_color(status)),
                     )]))
                     .block(Block::new())
@@ -154,5 +308,277 @@ impl App {
                 );
             }
         }
+
+        if let Some((message, shown)) = &self.message
+            && shown.elapsed() < MESSAGE_DURATION
+        {
+            frame.render_widget(Clear, info);
+            frame.render_widget(
+                Paragraph::new(Line::from(Span::styled(
+                    message.as_str(),
+                    Style::default().fg(self.get_color(&self.config.colors.highlight_color)),
+                )))
+                .alignment(Alignment::Center),
+                info,
+            );
+        }
+
+        if let Some(popup) = &self.popup {
+            self.render_popup(frame, popup);
+        }
+    }
+
+    /// Draws a popup centered over everything else.
+    fn render_popup(&self, frame: &mut Frame, popup: &Popup) {
+        let border = &self.config.colors.border;
+        let status = &self.config.colors.status;
+
+        let (title, lines) = match popup {
+            Popup::Confirm { message, .. } => (
+                " confirm ",
+                vec![
+                    Line::from(message.as_str()),
+                    Line::from(""),
+                    Line::from("y: yes    n: no"),
+                ],
+            ),
+            Popup::Input { prompt, input, .. } => (
+                " input ",
+                vec![
+                    Line::from(prompt.as_str()),
+                    Line::from(format!("> {}_", input)),
+                    Line::from(""),
+                    Line::from("enter: ok    esc: cancel"),
+                ],
+            ),
+            Popup::Generated { reading: true, .. } => (
+                " generated playlist ",
+                vec![
+                    Line::from("Reading tags..."),
+                    Line::from(""),
+                    Line::from("esc: cancel"),
+                ],
+            ),
+            Popup::Generated {
+                target_secs,
+                tracks,
+                ..
+            } => {
+                let mut lines = vec![Line::from(match tracks.is_empty() {
+                    true => "No tracks of known length matched".to_string(),
+                    false => format!(
+                        "{} tracks, {} (target {})",
+                        tracks.len(),
+                        FileMetadata::format_secs(duration_of(tracks)),
+                        FileMetadata::format_secs(*target_secs)
+                    ),
+                })];
+                lines.push(Line::from(""));
+                lines.extend(tracks.iter().take(GENERATED_PREVIEW_LINES).map(|track| {
+                    Line::from(format!(
+                        "{} - {}  {}",
+                        track.display_artist(),
+                        track.display_title(),
+                        track.display_duration_display()
+                    ))
+                }));
+                if GENERATED_PREVIEW_LINES < tracks.len() {
+                    lines.push(Line::from(format!(
+                        "... and {} more",
+                        tracks.len() - GENERATED_PREVIEW_LINES
+                    )));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::from(
+                    "r: replace queue    a: append    g: regenerate    esc: cancel",
+                ));
+                (" generated playlist ", lines)
+            }
+            Popup::Repair {
+                playlist,
+                searching: true,
+                ..
+            } => {
+                let lines = vec![
+                    Line::from(format!(
+                        "Some entries in {} are missing, looking for replacements in {}...",
+                        self.format_display_path(playlist),
+                        self.format_display_path(&self.config.directories.music_directory)
+                    )),
+                    Line::from(""),
+                    Line::from("i: load as is    esc: cancel"),
+                ];
+                (" repair playlist ", lines)
+            }
+            Popup::Repair {
+                items,
+                playlist,
+                repairs,
+                selected,
+                ..
+            } => {
+                let found = repairs
+                    .iter()
+                    .filter(|repair| !repair.candidates.is_empty())
+                    .count();
+                let mut lines = vec![
+                    Line::from(format!(
+                        "{} of {} entries in {} are missing, replacements found for {}",
+                        repairs.len(),
+                        items.len(),
+                        self.format_display_path(playlist),
+                        found
+                    )),
+                    Line::from(""),
+                ];
+                let first = selected
+                    .saturating_sub(GENERATED_PREVIEW_LINES / 2)
+                    .min(repairs.len().saturating_sub(GENERATED_PREVIEW_LINES));
+                for (index, repair) in repairs
+                    .iter()
+                    .enumerate()
+                    .skip(first)
+                    .take(GENERATED_PREVIEW_LINES)
+                {
+                    let missing = self.format_display_path(&items[repair.index].file_path);
+                    let text = match repair.replacement() {
+                        Some(replacement) => {
+                            let choice = match repair.candidates.len() {
+                                1 => String::new(),
+                                count => format!(", {} of {}", repair.chosen + 1, count),
+                            };
+                            format!(
+                                "[{}] {} -> {} (by {}{})",
+                                if repair.accepted { "x" } else { " " },
+                                missing,
+                                self.format_display_path(&replacement.file_path),
+                                repair.kind.describe(),
+                                choice
+                            )
+                        }
+                        None => format!("[ ] {}: nothing found", missing),
+                    };
+                    lines.push(match index == *selected {
+                        true => Line::styled(
+                            text,
+                            Style::default()
+                                .fg(self.get_color(&self.config.colors.highlight_color)),
+                        ),
+                        false => Line::from(text),
+                    });
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::from(
+                    "space: toggle    h/l: other match    enter: repair and save    i: load as is    esc: cancel",
+                ));
+                (" repair playlist ", lines)
+            }
+            Popup::Inspector {
+                info,
+                reading,
+                track,
+                ..
+            } => (
+                " track inspector ",
+                self.inspector_lines(info.as_ref(), *reading, track),
+            ),
+        };
+        // Field lists read better from the left, and only the inspector is long enough to scroll.
+        let (alignment, scroll) = match popup {
+            Popup::Inspector { scroll, .. } => (Alignment::Left, *scroll),
+            _ => (Alignment::Center, 0),
+        };
+
+        let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .style(Style::default().fg(self.get_color(status)))
+                .block(
+                    Block::new()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(self.get_color(border)))
+                        .border_type(BorderType::Rounded)
+                        .title(title)
+                        .padding(Padding::horizontal(1)),
+                )
+                .alignment(alignment)
+                .wrap(Wrap { trim: true })
+                .scroll((scroll, 0)),
+            area,
+        );
+    }
+
+    /// Lists what the inspector shows about a track, one line per field or tag.
+    pub fn inspector_lines(
+        &self,
+        info: Option<&StreamInfo>,
+        reading: bool,
+        track: &FileMetadata,
+    ) -> Vec<Line<'static>> {
+        // Skips fields the file doesn't have.
+        let field = |label: &str, value: String| {
+            (!value.is_empty()).then(|| Line::from(format!("{}: {}", label, value)))
+        };
+        let mut lines = vec![
+            Line::from(format!(
+                "{} - {}",
+                track.display_artist(),
+                track.display_title()
+            )),
+            Line::from(""),
+            Line::from(format!("path: {}", track.file_path.display())),
+        ];
+        match info {
+            None if reading => lines.push(Line::from("Reading the file...")),
+            Some(info) => {
+                lines.extend(
+                    [
+                        field("size", info.display_file_size()),
+                        field("codec", info.codec_name()),
+                        field("container", info.container.clone()),
+                        field(
+                            "sample rate",
+                            info.sample_rate
+                                .map(|_| format!("{} kHz", info.display_sample_rate()))
+                                .unwrap_or_default(),
+                        ),
+                        field(
+                            "bit depth",
+                            info.bit_depth
+                                .map(|bits| format!("{} bit", bits))
+                                .unwrap_or_default(),
+                        ),
+                        field("channels", info.display_channels()),
+                        field("bitrate", info.display_bitrate()),
+                    ]
+                    .into_iter()
+                    .flatten(),
+                );
+                lines.push(Line::from(""));
+                match info.replay_gain.is_empty() {
+                    true => lines.push(Line::from("replaygain: none")),
+                    false => lines.extend(info.replay_gain.iter().map(|(label, value)| {
+                        Line::from(format!("replaygain {}: {}", label, value))
+                    })),
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!("{} tags", info.tags.len())));
+                // Multi-line values such as lyrics are kept on one line.
+                lines.extend(info.tags.iter().map(|(key, value)| {
+                    Line::from(format!("{}: {}", key, value.replace(['\r', '\n'], " ")))
+                }));
+            }
+            None => lines.push(Line::from("The decoder can't read this file")),
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("j/k: scroll    esc: close"));
+        lines
     }
 }
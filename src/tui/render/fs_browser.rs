@@ -1,15 +1,36 @@
 use crate::{
     data::{
         config::{ConfigData, load_config},
-        metadata::file_metadata::FileMetadata,
+        library::scan::natural_cmp,
+        metadata::{
+            cue::{SheetTracks, cue_sheets_in},
+            file_metadata::FileMetadata,
+            loader::MetadataLoader,
+        },
     },
-    tui::app::PLAYABLE,
+    tui::app::{PLAYABLE, PLAYLISTS},
 };
 use ratatui::{
     style::{Color, Style},
     widgets::{ListItem, ListState},
 };
-use std::{collections::HashMap, error::Error, fs::read_dir, path::PathBuf, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs::{self, read_dir},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// What the current directory holds, as last read from disk.
+#[derive(Default)]
+struct Listing {
+    audio_files: Vec<PathBuf>,
+    cue_sheets: SheetTracks,
+    directories: Vec<PathBuf>,
+    playlists: Vec<PathBuf>,
+}
 
 /// Encapsulates file system browsing state and behavior.
 pub struct FileBrowser {
@@ -19,6 +40,14 @@ pub struct FileBrowser {
     pub list_state: ListState,
     pub sel_map: HashMap<PathBuf, usize>,
     pub selected: usize,
+    pub virtual_tracks: HashMap<usize, FileMetadata>,
+    listing: Listing,
+    /// The current directory's modification time when it was last listed.
+    listed_modified: Option<SystemTime>,
+    loaded: HashMap<PathBuf, Vec<FileMetadata>>,
+    loaded_dir: PathBuf,
+    loader: MetadataLoader,
+    requested: HashSet<PathBuf>,
 }
 
 impl FileBrowser {
@@ -35,11 +64,18 @@ impl FileBrowser {
             list_state,
             selected: 0,
             sel_map,
+            virtual_tracks: HashMap::new(),
+            listing: Listing::default(),
+            listed_modified: None,
+            loaded: HashMap::new(),
+            loaded_dir: PathBuf::new(),
+            loader: MetadataLoader::new(),
+            requested: HashSet::new(),
         }
     }
 
     /// Returns true if the file begins with '.'
-    fn is_hidden(path: &PathBuf) -> bool {
+    pub fn is_hidden(path: &Path) -> bool {
         path.file_name()
             .and_then(|n| n.to_str())
             .map(|s| s.starts_with('.'))
@@ -47,7 +83,7 @@ impl FileBrowser {
     }
 
     /// Returns true if the file's extention is in PLAYABLE
-    fn is_playable_file(path: &PathBuf, playable_exts: &[&str]) -> bool {
+    pub fn is_playable_file(path: &Path, playable_exts: &[&str]) -> bool {
         path.extension()
             .and_then(|ext| ext.to_str())
             .map(|ext| playable_exts.contains(&ext.to_ascii_lowercase().as_str()))
@@ -55,7 +91,26 @@ impl FileBrowser {
     }
 
     /// Refreshes the list of entries from the current directory.
+    /// The directory is only read again when it is a different one or something was added, removed or renamed in it.
+    /// Files whose tags aren't loaded yet are listed by name and asked for in the background.
     pub fn update_entries(&mut self) -> Result<(), Box<dyn Error>> {
+        let modified = fs::metadata(&self.current_dir)
+            .and_then(|stat| stat.modified())
+            .ok();
+        if self.loaded_dir == self.current_dir && self.listed_modified == modified {
+            self.list_state
+                .select((!self.entries.is_empty()).then_some(self.selected));
+            return Ok(());
+        }
+        self.listed_modified = modified;
+
+        if self.loaded_dir != self.current_dir {
+            self.loader.cancel_pending();
+            self.loaded.clear();
+            self.requested.clear();
+            self.loaded_dir = self.current_dir.clone();
+        }
+
         let (mut directories, audio_files): (Vec<PathBuf>, Vec<PathBuf>) =
             read_dir(&self.current_dir)?
                 .filter_map(|entry| {
@@ -68,30 +123,139 @@ impl FileBrowser {
 
         directories.sort_unstable();
 
-        let mut metadata_list: Vec<(u16, String, PathBuf)> = audio_files
+        let (mut playlists, mut audio_files): (Vec<PathBuf>, Vec<PathBuf>) = audio_files
             .into_iter()
-            .filter(|file| Self::is_playable_file(file, &PLAYABLE))
-            .map(|path| {
-                let file_data = FileMetadata::get_file_data(&path);
-                (
-                    file_data.track_number.unwrap_or(0),
-                    file_data.title.unwrap_or(file_data.raw_file),
-                    path,
-                )
+            .partition(|file| Self::is_playable_file(file, &PLAYLISTS));
+        playlists.sort_unstable();
+        audio_files.retain(|file| Self::is_playable_file(file, &PLAYABLE));
+        audio_files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+
+        for path in &audio_files {
+            if !self.loaded.contains_key(path) && self.requested.insert(path.clone()) {
+                self.loader.request(path.clone());
+            }
+        }
+
+        self.listing = Listing {
+            audio_files,
+            cue_sheets: cue_sheets_in(&self.current_dir),
+            directories,
+            playlists,
+        };
+        self.arrange_entries();
+        Ok(())
+    }
+
+    /// Takes in the tags the loader finished, re-sorting the entries if any belong to the current directory.
+    pub fn poll_metadata(&mut self) {
+        let mut arrived = false;
+        for (path, tracks) in self.loader.finished() {
+            if self.requested.remove(&path) {
+                self.loaded.insert(path, tracks);
+                arrived = true;
+            }
+        }
+        if arrived {
+            self.arrange_entries();
+        }
+    }
+
+    /// Lays out the entries: directories, then playlists, then audio files by track number.
+    /// Files not loaded yet keep their place by name. The cursor stays on the same entry.
+    fn arrange_entries(&mut self) {
+        let selected = self.entries.get(self.selected).cloned().map(|path| {
+            let start = self
+                .virtual_tracks
+                .get(&self.selected)
+                .map(|track| track.id().start_millis);
+            (path, start.flatten())
+        });
+
+        // Files described by a CUE sheet are listed as the sheet's tracks instead.
+        let mut metadata_list: Vec<(u16, PathBuf, Option<FileMetadata>)> = self
+            .listing
+            .audio_files
+            .iter()
+            .flat_map(|path| {
+                let tracks = self
+                    .listing
+                    .cue_sheets
+                    .get(path)
+                    .or_else(|| self.loaded.get(path));
+                match tracks {
+                    Some(tracks) if tracks.iter().any(|track| track.cue.is_some()) => tracks
+                        .iter()
+                        .map(|track| {
+                            (
+                                track.track_number.unwrap_or(0),
+                                path.clone(),
+                                Some(track.clone()),
+                            )
+                        })
+                        .collect(),
+                    Some(tracks) => vec![(
+                        tracks
+                            .first()
+                            .and_then(|track| track.track_number)
+                            .unwrap_or(0),
+                        path.clone(),
+                        None,
+                    )],
+                    None => vec![(0, path.clone(), None)],
+                }
             })
             .collect();
 
-        metadata_list.sort_unstable_by_key(|entry| entry.0);
+        // Stable, so a sheet's tracks stay in order and files without numbers stay by name.
+        metadata_list.sort_by_key(|entry| entry.0);
 
-        self.entries = directories
-            .into_iter()
-            .chain(metadata_list.into_iter().map(|(_, _, path)| path))
+        let listed = self.listing.directories.len() + self.listing.playlists.len();
+        self.virtual_tracks = metadata_list
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, (_, _, track))| track.take().map(|track| (listed + index, track)))
+            .collect();
+        self.entries = self
+            .listing
+            .directories
+            .iter()
+            .chain(&self.listing.playlists)
+            .cloned()
+            .chain(metadata_list.into_iter().map(|(_, path, _)| path))
             .collect();
 
+        if let Some(index) = selected.and_then(|(path, start)| {
+            (0..self.entries.len()).find(|&index| {
+                self.entries[index] == path
+                    && self
+                        .virtual_tracks
+                        .get(&index)
+                        .and_then(|track| track.id().start_millis)
+                        == start
+            })
+        }) {
+            self.selected = index;
+            self.sel_map.insert(self.current_dir.clone(), index);
+        }
         self.list_state
             .select((!self.entries.is_empty()).then_some(self.selected));
+    }
 
-        Ok(())
+    /// Returns the metadata of the file entry at `index`; CUE tracks get their own.
+    pub fn track_at(&self, index: usize) -> Option<FileMetadata> {
+        match self.virtual_tracks.get(&index) {
+            Some(track) => Some(track.clone()),
+            None => self
+                .entries
+                .get(index)
+                .filter(|path| !path.is_dir())
+                .map(|path| {
+                    self.loaded
+                        .get(path)
+                        .and_then(|tracks| tracks.first().cloned())
+                        .unwrap_or_else(|| FileMetadata::get_file_data(path))
+                }),
+        }
     }
 
     /// Moves the cursor up one element or goes to the bottom if at the top.
@@ -122,11 +286,11 @@ impl FileBrowser {
 
     /// Navigates into the selected directory, either setting the cursor to the saved position or 0.
     pub fn navigate_into(&mut self) {
-        if let Some(path) = self.entries.get(self.selected) {
-            if path.is_dir() {
-                self.current_dir = path.clone();
-                self.selected = *self.sel_map.get(&self.current_dir).unwrap_or(&0);
-            }
+        if let Some(path) = self.entries.get(self.selected)
+            && path.is_dir()
+        {
+            self.current_dir = path.clone();
+            self.selected = *self.sel_map.get(&self.current_dir).unwrap_or(&0);
         }
     }
 
@@ -169,8 +333,11 @@ impl FileBrowser {
 
         self.entries
             .iter()
-            .map(|entry| {
-                let (display_name, style) = if entry.is_dir() {
+            .enumerate()
+            .map(|(index, entry)| {
+                let (display_name, style) = if let Some(track) = self.virtual_tracks.get(&index) {
+                    (track.display_title(), file_style)
+                } else if entry.is_dir() {
                     (
                         entry
                             .file_name()
@@ -179,8 +346,21 @@ impl FileBrowser {
                         dir_style,
                     )
                 } else {
-                    let file_data = FileMetadata::get_file_data(entry);
-                    (file_data.title.unwrap_or(file_data.raw_file), file_style)
+                    // Until its tags arrive a file shows its name.
+                    let title = self
+                        .loaded
+                        .get(entry)
+                        .and_then(|tracks| tracks.first())
+                        .and_then(|track| track.title.clone());
+                    (
+                        title.unwrap_or_else(|| {
+                            entry
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or("Unknown".to_string())
+                        }),
+                        file_style,
+                    )
                 };
                 ListItem::new(display_name).style(style)
             })
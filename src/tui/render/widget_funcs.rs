@@ -1,4 +1,7 @@
-use crate::tui::app::{App, Tab};
+use crate::{
+    data::metadata::file_metadata::FileMetadata,
+    tui::app::{App, Tab},
+};
 use ratatui::{
     layout::Alignment,
     style::Style,
@@ -8,6 +11,7 @@ use ratatui::{
 
 impl App {
     pub fn top_left(&self) -> impl Widget {
+        let data = self.current_data();
         let border = &self.config.colors.border;
         let paused = &self.config.colors.paused;
         let timestamp = &self.config.colors.timestamp;
@@ -16,28 +20,33 @@ impl App {
             Line::from(vec![Span::styled(
                 if self.audio.is_empty() {
                     String::new()
+                } else if let (true, Some(duration)) = (self.show_remaining, data.duration_as_secs)
+                {
+                    format!(
+                        "-{}/{}",
+                        FileMetadata::format_secs(duration - self.audio.sink_pos() as f64),
+                        data.display_duration_display() // Total time
+                    )
                 } else {
                     format!(
                         "{:.0}:{:02.0}/{}",
                         self.audio.sink_pos() / 60, // Minutes
                         self.audio.sink_pos() % 60, // Seconds
                         // Seperate function since the display could be None
-                        self.data.display_duration_display() // Total time
+                        data.display_duration_display() // Total time
                     )
                 },
                 Style::default().fg(self.get_color(timestamp)),
             )]),
             Line::from(vec![Span::styled(
-                format!(
-                    "{}",
-                    match self.audio.is_empty() {
-                        true => "stopped",
-                        false => match self.audio.paused {
-                            true => "paused",
-                            false => "playing",
-                        },
-                    }
-                ),
+                match self.audio.is_empty() {
+                    _ if self.audio.previewing => "preview",
+                    true => "stopped",
+                    false => match self.audio.paused {
+                        true => "paused",
+                        false => "playing",
+                    },
+                },
                 Style::default().fg(self.get_color(paused)),
             )]),
         ])
@@ -52,6 +61,7 @@ impl App {
     }
 
     pub fn top_center(&self) -> impl Widget {
+        let data = self.current_data();
         let album = &self.config.colors.album;
         let artist = &self.config.colors.artist;
         let border = &self.config.colors.border;
@@ -60,37 +70,57 @@ impl App {
         let title = &self.config.colors.title;
         let track_num = &self.config.colors.track_num;
         let year = &self.config.colors.year;
+        let composer = &self.config.colors.composer;
+        let disc_num = &self.config.colors.disc_num;
+        let genre = &self.config.colors.genre;
+        let bpm = &self.config.colors.bpm;
+        let format = &self.config.colors.format;
+
+        // Joins the non-empty fields with spaces.
+        let line = |fields: Vec<(String, &String)>| {
+            let spans: Vec<Span> = fields
+                .into_iter()
+                .filter(|(text, _)| !text.is_empty())
+                .flat_map(|(text, color)| {
+                    [
+                        Span::from(" "),
+                        Span::styled(text, Style::default().fg(self.get_color(color))),
+                    ]
+                })
+                .skip(1)
+                .collect();
+            Line::from(spans)
+        };
 
         Paragraph::new(if self.audio.is_empty() {
             vec![Line::from("")]
         } else {
             vec![
-                Line::from(vec![
-                    Span::styled(
-                        format!("{}", self.data.display_artist()),
-                        Style::default().fg(self.get_color(artist)),
-                    ),
-                    Span::from(" "),
-                    Span::styled(
-                        format!("{}", self.data.display_title()),
-                        Style::default().fg(self.get_color(title)),
+                line(vec![
+                    (data.display_artist(), artist),
+                    (data.display_title(), title),
+                    (
+                        data.composer
+                            .as_ref()
+                            .map(|name| format!("({})", name))
+                            .unwrap_or_default(),
+                        composer,
                     ),
                 ]),
-                Line::from(vec![
-                    Span::styled(
-                        format!("{}", self.data.display_album()),
-                        Style::default().fg(self.get_color(album)),
-                    ),
-                    Span::from(" "),
-                    Span::styled(
-                        format!("{}", self.data.display_year()),
-                        Style::default().fg(self.get_color(year)),
-                    ),
-                    Span::from(" "),
-                    Span::styled(
-                        format!("{}", self.data.display_track_number()),
-                        Style::default().fg(self.get_color(track_num)),
+                line(vec![
+                    (data.display_album(), album),
+                    (data.display_year(), year),
+                    (data.display_track_of_total(), track_num),
+                    // Only multi-disc releases show the disc.
+                    (
+                        match data.disc_total.is_some_and(|total| 1 < total) {
+                            true => format!("cd {}", data.display_disc_number()),
+                            false => String::new(),
+                        },
+                        disc_num,
                     ),
+                    (data.display_genre(), genre),
+                    (data.display_bpm(), bpm),
                 ]),
             ]
         })
@@ -99,25 +129,65 @@ impl App {
                 .borders(Borders::TOP | Borders::BOTTOM)
                 .border_style(Style::default().fg(self.get_color(border)))
                 .border_type(BorderType::Rounded)
+                .title_top(match &self.format_badge {
+                    Some((_, badge)) if !self.audio.is_empty() && !badge.is_empty() => {
+                        Line::from(vec![
+                            Span::styled("┤", self.get_color(border)),
+                            Span::styled(
+                                format!(" {} ", badge),
+                                Style::default().fg(self.get_color(format)),
+                            ),
+                            Span::styled("├", self.get_color(border)),
+                        ])
+                        .right_aligned()
+                    }
+                    _ => Line::from(""),
+                })
                 .title_bottom(
-                    Line::from(vec![
-                        Span::styled("┤", self.get_color(border)),
-                        Span::styled(
-                            " 1 ",
-                            match self.tab {
-                                Tab::Browser => Style::default().fg(self.get_color(tab_selected)),
-                                _ => Style::default().fg(self.get_color(tab_unselected)),
-                            },
-                        ),
-                        Span::styled(
-                            " 2 ",
-                            match self.tab {
-                                Tab::Playlist => Style::default().fg(self.get_color(tab_selected)),
-                                _ => Style::default().fg(self.get_color(tab_unselected)),
-                            },
-                        ),
-                        Span::styled("├", self.get_color(border)),
-                    ])
+                    Line::from(
+                        [
+                            Span::styled("┤", self.get_color(border)),
+                            Span::styled(
+                                " 1 ",
+                                match self.tab {
+                                    Tab::Browser => {
+                                        Style::default().fg(self.get_color(tab_selected))
+                                    }
+                                    _ => Style::default().fg(self.get_color(tab_unselected)),
+                                },
+                            ),
+                            Span::styled(
+                                " 2 ",
+                                match self.tab {
+                                    Tab::Playlist => {
+                                        Style::default().fg(self.get_color(tab_selected))
+                                    }
+                                    _ => Style::default().fg(self.get_color(tab_unselected)),
+                                },
+                            ),
+                            Span::styled(
+                                " 3 ",
+                                match self.tab {
+                                    Tab::Saved => Style::default().fg(self.get_color(tab_selected)),
+                                    _ => Style::default().fg(self.get_color(tab_unselected)),
+                                },
+                            ),
+                            Span::styled("│", self.get_color(border)),
+                        ]
+                        .into_iter()
+                        // The queues, with the active one highlighted.
+                        .chain(self.queues.iter().enumerate().map(|(index, named)| {
+                            Span::styled(
+                                format!(" {} ", named.name),
+                                match index == self.active_queue {
+                                    true => Style::default().fg(self.get_color(tab_selected)),
+                                    false => Style::default().fg(self.get_color(tab_unselected)),
+                                },
+                            )
+                        }))
+                        .chain([Span::styled("├", self.get_color(border))])
+                        .collect::<Vec<_>>(),
+                    )
                     .centered(),
                 ),
         )
@@ -126,12 +196,22 @@ impl App {
 
     pub fn top_right(&self) -> impl Widget {
         let border = &self.config.colors.border;
+        let options = &self.config.colors.options;
         let volume = &self.config.colors.volume;
 
-        Paragraph::new(vec![Line::from(vec![Span::styled(
-            format!("{}%", self.audio.vol),
-            Style::default().fg(self.get_color(volume)),
-        )])])
+        Paragraph::new(vec![
+            Line::from(vec![Span::styled(
+                format!("{}%", self.audio.vol),
+                Style::default().fg(self.get_color(volume)),
+            )]),
+            Line::from(vec![Span::styled(
+                match self.auto_dj {
+                    true => format!("{}[dj]", self.queue.modes.flags()),
+                    false => self.queue.modes.flags(),
+                },
+                Style::default().fg(self.get_color(options)),
+            )]),
+        ])
         .block(
             Block::new()
                 .borders(Borders::TOP | Borders::BOTTOM | Borders::RIGHT)
@@ -143,26 +223,21 @@ impl App {
     }
 
     pub fn progress_bar(&self) -> impl Widget {
+        let data = self.current_data();
         let seekbar_filled = &self.config.colors.seekbar_filled;
         let seekbar_unfilled = &self.config.colors.seekbar_unfilled;
 
         LineGauge::default()
             .block(Block::new())
             .label("")
-            .ratio({
-                if self.audio.is_empty() {
-                    0.
-                } else {
-                    (self.audio.sink_pos_millis() as f64
-                        / (self
-                            .data
-                            .duration_as_secs
-                            .expect("This shouldn't fail if the file is loaded correctly")
-                            * 1000.0))
-                        .clamp(0., 1.)
+            .ratio(match data.duration_as_secs {
+                // The duration is only missing if the decoder couldn't read the file either.
+                Some(duration) if !self.audio.is_empty() && duration > 0. => {
+                    (self.audio.sink_pos_millis() as f64 / (duration * 1000.0)).clamp(0., 1.)
                 }
+                _ => 0.,
             })
-            .filled_style(Style::default().fg(self.get_color(&seekbar_filled)))
-            .unfilled_style(Style::default().fg(self.get_color(&seekbar_unfilled)))
+            .filled_style(Style::default().fg(self.get_color(seekbar_filled)))
+            .unfilled_style(Style::default().fg(self.get_color(seekbar_unfilled)))
     }
 }